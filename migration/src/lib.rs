@@ -1,6 +1,17 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20250613_192747_create_movie_tables;
+mod m20261019_090000_add_movie_year;
+mod m20261019_090100_create_autocomplete_indexes;
+mod m20261019_090200_add_external_ids;
+mod m20261019_090300_create_api_keys;
+mod m20261019_090400_create_users;
+mod m20261019_090500_add_roles;
+mod m20261019_090600_add_soft_delete;
+mod m20261019_090700_add_change_tracking;
+mod m20261019_090800_create_movie_revisions;
+mod m20261019_090900_create_audit_log;
+mod m20261019_091000_add_versions;
 
 pub struct Migrator;
 
@@ -9,6 +20,17 @@ impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
         vec![
             Box::new(m20250613_192747_create_movie_tables::Migration),
+            Box::new(m20261019_090000_add_movie_year::Migration),
+            Box::new(m20261019_090100_create_autocomplete_indexes::Migration),
+            Box::new(m20261019_090200_add_external_ids::Migration),
+            Box::new(m20261019_090300_create_api_keys::Migration),
+            Box::new(m20261019_090400_create_users::Migration),
+            Box::new(m20261019_090500_add_roles::Migration),
+            Box::new(m20261019_090600_add_soft_delete::Migration),
+            Box::new(m20261019_090700_add_change_tracking::Migration),
+            Box::new(m20261019_090800_create_movie_revisions::Migration),
+            Box::new(m20261019_090900_create_audit_log::Migration),
+            Box::new(m20261019_091000_add_versions::Migration),
         ]
     }
 }
@@ -0,0 +1,36 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Año de estreno (opcional, las películas existentes quedan a NULL)
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Movies::Table)
+                    .add_column(ColumnDef::new(Movies::Year).integer().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Movies::Table)
+                    .drop_column(Movies::Year)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Movies {
+    Table,
+    Year,
+}
@@ -0,0 +1,37 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// SQLite solo usa un índice para `LIKE 'abc%'` si la columna está indexada con
+// COLLATE NOCASE (LIKE no distingue mayúsculas por defecto), y sea-query no
+// permite indicar la collation en `Index::create`, así que se escribe a mano.
+const INDEXES: [(&str, &str, &str); 4] = [
+    ("idx_movies_title_nocase", "movies", "title"),
+    ("idx_directors_name_nocase", "directors", "name"),
+    ("idx_actors_name_nocase", "actors", "name"),
+    ("idx_genres_name_nocase", "genres", "name"),
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        for (name, table, column) in INDEXES {
+            db.execute_unprepared(&format!(
+                "CREATE INDEX IF NOT EXISTS {name} ON {table} ({column} COLLATE NOCASE)"
+            ))
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        for (name, _, _) in INDEXES {
+            db.execute_unprepared(&format!("DROP INDEX IF EXISTS {name}"))
+                .await?;
+        }
+        Ok(())
+    }
+}
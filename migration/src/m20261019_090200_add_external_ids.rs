@@ -0,0 +1,61 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// Identificador en un catálogo externo (p. ej. IMDb) para que las importaciones
+// puedan volver a encontrar la misma fila. Es opcional y único cuando existe.
+const TABLES: [&str; 4] = ["movies", "directors", "actors", "genres"];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in TABLES {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Alias::new(table))
+                        .add_column(ColumnDef::new(ExternalId).string().null())
+                        .to_owned(),
+                )
+                .await?;
+            // SQLite no admite UNIQUE en ADD COLUMN: se crea un índice aparte.
+            manager
+                .create_index(
+                    Index::create()
+                        .name(format!("idx_{table}_external_id"))
+                        .table(Alias::new(table))
+                        .col(ExternalId)
+                        .unique()
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in TABLES {
+            manager
+                .drop_index(
+                    Index::drop()
+                        .name(format!("idx_{table}_external_id"))
+                        .table(Alias::new(table))
+                        .to_owned(),
+                )
+                .await?;
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Alias::new(table))
+                        .drop_column(ExternalId)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+struct ExternalId;
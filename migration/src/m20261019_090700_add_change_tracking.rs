@@ -0,0 +1,97 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// Cuándo y quién creó y modificó por última vez cada fila. Los rellena
+// `before_save` en `models.rs`; las filas anteriores a esta migración se quedan
+// sin valor porque no se sabe cuándo se crearon.
+const TABLES: [&str; 4] = ["movies", "directors", "actors", "genres"];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in TABLES {
+            // SQLite solo admite una columna por ALTER TABLE.
+            let columns = [
+                ColumnDef::new(Tracking::CreatedAt)
+                    .timestamp_with_time_zone()
+                    .null()
+                    .to_owned(),
+                ColumnDef::new(Tracking::UpdatedAt)
+                    .timestamp_with_time_zone()
+                    .null()
+                    .to_owned(),
+                ColumnDef::new(Tracking::CreatedBy)
+                    .string()
+                    .null()
+                    .to_owned(),
+                ColumnDef::new(Tracking::UpdatedBy)
+                    .string()
+                    .null()
+                    .to_owned(),
+            ];
+            for mut column in columns {
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(Alias::new(table))
+                            .add_column(&mut column)
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+            for column in [Tracking::CreatedAt, Tracking::UpdatedAt] {
+                manager
+                    .create_index(
+                        Index::create()
+                            .name(format!("idx_{table}_{}", column.to_string()))
+                            .table(Alias::new(table))
+                            .col(column)
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for table in TABLES {
+            for column in [Tracking::CreatedAt, Tracking::UpdatedAt] {
+                manager
+                    .drop_index(
+                        Index::drop()
+                            .name(format!("idx_{table}_{}", column.to_string()))
+                            .table(Alias::new(table))
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+            for column in [
+                Tracking::CreatedAt,
+                Tracking::UpdatedAt,
+                Tracking::CreatedBy,
+                Tracking::UpdatedBy,
+            ] {
+                manager
+                    .alter_table(
+                        Table::alter()
+                            .table(Alias::new(table))
+                            .drop_column(column)
+                            .to_owned(),
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Iden, Clone, Copy)]
+enum Tracking {
+    CreatedAt,
+    UpdatedAt,
+    CreatedBy,
+    UpdatedBy,
+}
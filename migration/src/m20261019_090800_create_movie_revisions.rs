@@ -0,0 +1,90 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// Historial de cambios de las películas: cada alta, modificación o reversión guarda
+// una instantánea completa (título, año, director, reparto y géneros) numerada por
+// película. Se borra con la película al purgarla.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(MovieRevisions::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(MovieRevisions::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(MovieRevisions::MovieId).integer().not_null())
+                    .col(
+                        ColumnDef::new(MovieRevisions::Revision)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(MovieRevisions::Action).string().not_null())
+                    .col(
+                        ColumnDef::new(MovieRevisions::RevertedFrom)
+                            .integer()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(MovieRevisions::Snapshot).json().not_null())
+                    .col(
+                        ColumnDef::new(MovieRevisions::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(MovieRevisions::CreatedBy).string().null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_movie_revisions_movie")
+                            .from(MovieRevisions::Table, MovieRevisions::MovieId)
+                            .to(Movies::Table, Movies::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_movie_revisions_movie_revision")
+                    .table(MovieRevisions::Table)
+                    .col(MovieRevisions::MovieId)
+                    .col(MovieRevisions::Revision)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(MovieRevisions::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum MovieRevisions {
+    Table,
+    Id,
+    MovieId,
+    Revision,
+    Action,
+    RevertedFrom,
+    Snapshot,
+    CreatedAt,
+    CreatedBy,
+}
+
+#[derive(Iden)]
+enum Movies {
+    Table,
+    Id,
+}
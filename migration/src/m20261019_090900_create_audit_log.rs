@@ -0,0 +1,90 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// Registro de auditoría: una fila por cada alta, modificación, borrado,
+// restauración o purga de películas, directores, actores, géneros y sus
+// relaciones, con el estado anterior y el posterior en JSON. Es de solo
+// inserción: los triggers rechazan cualquier UPDATE o DELETE.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AuditLog::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AuditLog::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(AuditLog::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(AuditLog::Actor).string().null())
+                    .col(ColumnDef::new(AuditLog::RequestId).string().null())
+                    .col(ColumnDef::new(AuditLog::Action).string().not_null())
+                    .col(ColumnDef::new(AuditLog::Entity).string().not_null())
+                    .col(ColumnDef::new(AuditLog::EntityId).string().not_null())
+                    .col(ColumnDef::new(AuditLog::Before).json().null())
+                    .col(ColumnDef::new(AuditLog::After).json().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        let indexes = [
+            (
+                "idx_audit_log_entity",
+                vec![AuditLog::Entity, AuditLog::EntityId],
+            ),
+            ("idx_audit_log_actor", vec![AuditLog::Actor]),
+            ("idx_audit_log_created_at", vec![AuditLog::CreatedAt]),
+        ];
+        for (name, columns) in indexes {
+            let mut index = Index::create();
+            index.name(name).table(AuditLog::Table);
+            for column in columns {
+                index.col(column);
+            }
+            manager.create_index(index.to_owned()).await?;
+        }
+
+        let db = manager.get_connection();
+        for operation in ["UPDATE", "DELETE"] {
+            db.execute_unprepared(&format!(
+                "CREATE TRIGGER IF NOT EXISTS audit_log_no_{op} BEFORE {OP} ON audit_log \
+                 BEGIN SELECT RAISE(ABORT, 'audit_log is append-only'); END",
+                op = operation.to_lowercase(),
+                OP = operation,
+            ))
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuditLog::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden, Clone, Copy)]
+enum AuditLog {
+    Table,
+    Id,
+    CreatedAt,
+    Actor,
+    RequestId,
+    Action,
+    Entity,
+    EntityId,
+    Before,
+    After,
+}
@@ -0,0 +1,285 @@
+// Registro de auditoría de las escrituras en el catálogo.
+//
+// Cada alta, modificación, borrado, restauración o purga de una película, un
+// director, un actor, un género o una de sus relaciones añade una fila a
+// `audit_log` con el autor, el identificador de la petición y el estado anterior
+// y posterior en JSON. Se escribe en la misma transacción que el cambio, así que
+// no hay cambios sin anotar ni anotaciones de cambios deshechos. La tabla es de
+// solo inserción (la migración lo impide con triggers).
+
+use crate::auth::{Authorized, current_actor, require};
+use crate::models::{
+    AuditAction, Versioned, actor, audit_log, director, genre, movie, movie_actor, movie_genre,
+};
+use crate::route_handler::{Meta, parse_since};
+use crate::telemetry::current_request_id;
+use actix_web::{HttpResponse, Responder, web};
+use chrono::Utc;
+use sea_orm::ActiveValue::Set;
+use sea_orm::prelude::DateTimeUtc;
+use sea_orm::{
+    ActiveModelBehavior, ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait,
+    DatabaseConnection, DbErr, EntityTrait, IntoActiveModel, PaginatorTrait, QueryFilter,
+    QueryOrder,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::{IntoParams, ToSchema};
+
+/// Fila que se anota en el registro de auditoría.
+pub trait Audited: Serialize {
+    /// Nombre de la entidad en el registro.
+    const ENTITY: &'static str;
+
+    /// Identificador de la fila; en las relaciones, `<movie_id>:<id>`.
+    fn audit_id(&self) -> String;
+}
+
+impl Audited for movie::Model {
+    const ENTITY: &'static str = "movie";
+    fn audit_id(&self) -> String {
+        self.id.to_string()
+    }
+}
+
+impl Audited for director::Model {
+    const ENTITY: &'static str = "director";
+    fn audit_id(&self) -> String {
+        self.id.to_string()
+    }
+}
+
+impl Audited for actor::Model {
+    const ENTITY: &'static str = "actor";
+    fn audit_id(&self) -> String {
+        self.id.to_string()
+    }
+}
+
+impl Audited for genre::Model {
+    const ENTITY: &'static str = "genre";
+    fn audit_id(&self) -> String {
+        self.id.to_string()
+    }
+}
+
+impl Audited for movie_actor::Model {
+    const ENTITY: &'static str = "movie_actor";
+    fn audit_id(&self) -> String {
+        format!("{}:{}", self.movie_id, self.actor_id)
+    }
+}
+
+impl Audited for movie_genre::Model {
+    const ENTITY: &'static str = "movie_genre";
+    fn audit_id(&self) -> String {
+        format!("{}:{}", self.movie_id, self.genre_id)
+    }
+}
+
+fn to_json<M: Serialize>(model: Option<&M>) -> Result<Option<Value>, DbErr> {
+    model
+        .map(serde_json::to_value)
+        .transpose()
+        .map_err(|e| DbErr::Json(e.to_string()))
+}
+
+/// Anota un cambio de una fila; `before` o `after` falta en altas y purgas.
+/// Llamar en la misma transacción que el cambio.
+pub async fn record<C: ConnectionTrait, M: Audited>(
+    db: &C,
+    action: AuditAction,
+    before: Option<&M>,
+    after: Option<&M>,
+) -> Result<(), DbErr> {
+    let Some(entity_id) = after.or(before).map(Audited::audit_id) else {
+        return Ok(());
+    };
+    audit_log::ActiveModel {
+        created_at: Set(Utc::now()),
+        actor: Set(current_actor()),
+        request_id: Set(current_request_id()),
+        action: Set(action),
+        entity: Set(M::ENTITY.to_string()),
+        entity_id: Set(entity_id),
+        before: Set(to_json(before)?),
+        after: Set(to_json(after)?),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    Ok(())
+}
+
+/// Inserta `active` y anota el alta.
+pub async fn insert<C, A>(db: &C, active: A) -> Result<<A::Entity as EntityTrait>::Model, DbErr>
+where
+    C: ConnectionTrait,
+    A: ActiveModelTrait + ActiveModelBehavior + Send,
+    <A::Entity as EntityTrait>::Model: IntoActiveModel<A> + Audited,
+{
+    let model = active.insert(db).await?;
+    record(db, AuditAction::Create, None, Some(&model)).await?;
+    Ok(model)
+}
+
+/// Guarda los cambios de `active` y anota la modificación de `before`. Solo
+/// escribe si la fila sigue en la versión de `before`; si otro la ha cambiado
+/// entretanto, devuelve `DbErr::RecordNotUpdated`.
+pub async fn update<C, A>(
+    db: &C,
+    before: &<A::Entity as EntityTrait>::Model,
+    active: A,
+) -> Result<<A::Entity as EntityTrait>::Model, DbErr>
+where
+    C: ConnectionTrait,
+    A: ActiveModelTrait + ActiveModelBehavior + Send,
+    A::Entity: Versioned,
+    <A::Entity as EntityTrait>::Model: IntoActiveModel<A> + Audited,
+{
+    // Lo mismo que `ActiveModelTrait::update`, con la versión en el WHERE.
+    let active = active.before_save(db, false).await?;
+    let model = A::Entity::update(active)
+        .filter(<A::Entity as Versioned>::VERSION.eq(<A::Entity as Versioned>::version(before)))
+        .exec(db)
+        .await?;
+    let model = A::after_save(model, db, false).await?;
+    record(db, AuditAction::Update, Some(before), Some(&model)).await?;
+    Ok(model)
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AuditEntry {
+    pub id: i32,
+    pub created_at: DateTimeUtc,
+    /// `user:<id>`, `api-key:<id>` o `cli:<comando>`; vacío si se desconoce.
+    pub actor: Option<String>,
+    pub request_id: Option<String>,
+    pub action: AuditAction,
+    pub entity: String,
+    pub entity_id: String,
+    #[schema(value_type = Option<Object>)]
+    pub before: Option<Value>,
+    #[schema(value_type = Option<Object>)]
+    pub after: Option<Value>,
+}
+
+impl From<audit_log::Model> for AuditEntry {
+    fn from(model: audit_log::Model) -> Self {
+        AuditEntry {
+            id: model.id,
+            created_at: model.created_at,
+            actor: model.actor,
+            request_id: model.request_id,
+            action: model.action,
+            entity: model.entity,
+            entity_id: model.entity_id,
+            before: model.before,
+            after: model.after,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AuditPage {
+    pub meta: Meta,
+    pub results: Vec<AuditEntry>,
+}
+
+/// Filtros de `GET /admin/audit`.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditQuery {
+    /// `movie`, `director`, `actor`, `genre`, `movie_actor` o `movie_genre`
+    entity: Option<String>,
+    /// Id de la fila; en las relaciones, `<movie_id>:<id>`
+    entity_id: Option<String>,
+    /// Operación: `create`, `update`, `delete`, `restore` o `purge`
+    action: Option<AuditAction>,
+    /// Autor: `user:<id>`, `api-key:<id>` o `cli:<comando>`
+    actor: Option<String>,
+    /// Desde esta fecha (RFC 3339, o `AAAA-MM-DD` a las 00:00 UTC)
+    since: Option<String>,
+    /// Hasta esta fecha, excluida (RFC 3339, o `AAAA-MM-DD` a las 00:00 UTC)
+    until: Option<String>,
+    /// Página
+    page: Option<u32>,
+    /// Entradas por página (por defecto 50, como mucho 500)
+    per_page: Option<u32>,
+}
+
+impl AuditQuery {
+    fn condition(&self) -> Result<Condition, String> {
+        let mut condition = Condition::all();
+        if let Some(entity) = &self.entity {
+            condition = condition.add(audit_log::Column::Entity.eq(entity.as_str()));
+        }
+        if let Some(entity_id) = &self.entity_id {
+            condition = condition.add(audit_log::Column::EntityId.eq(entity_id.as_str()));
+        }
+        if let Some(action) = self.action {
+            condition = condition.add(audit_log::Column::Action.eq(action));
+        }
+        if let Some(actor) = &self.actor {
+            condition = condition.add(audit_log::Column::Actor.eq(actor.as_str()));
+        }
+        if let Some(since) = &self.since {
+            condition =
+                condition.add(audit_log::Column::CreatedAt.gte(parse_since("since", since)?));
+        }
+        if let Some(until) = &self.until {
+            condition =
+                condition.add(audit_log::Column::CreatedAt.lt(parse_since("until", until)?));
+        }
+        Ok(condition)
+    }
+}
+
+// GET /admin/audit
+#[utoipa::path(
+    get,
+    path = "/admin/audit",
+    responses(
+        (status = 200, description = "Entradas del registro de auditoría, las más recientes primero", body = AuditPage),
+        (status = 400, description = "Fecha mal formada"),
+        (status = 403, description = "Requiere rol admin", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    params(AuditQuery),
+    tag = "audit"
+)]
+pub async fn list_audit(
+    _auth: Authorized<require::Admin>,
+    db: web::Data<DatabaseConnection>,
+    query: web::Query<AuditQuery>,
+) -> impl Responder {
+    let condition = match query.condition() {
+        Ok(condition) => condition,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query.per_page.unwrap_or(50).clamp(1, 500);
+
+    let paginator = audit_log::Entity::find()
+        .filter(condition)
+        .order_by_desc(audit_log::Column::Id)
+        .paginate(db.get_ref(), per_page as u64);
+    let result = async {
+        let total = paginator.num_items().await?;
+        let entries = paginator.fetch_page((page - 1) as u64).await?;
+        Ok::<_, DbErr>((total, entries))
+    }
+    .await;
+    match result {
+        Ok((total, entries)) => HttpResponse::Ok().json(AuditPage {
+            meta: Meta {
+                total: total as usize,
+                page,
+                per_page,
+                last_page: total.div_ceil(per_page as u64).max(1) as u32,
+            },
+            results: entries.into_iter().map(AuditEntry::from).collect(),
+        }),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
@@ -0,0 +1,324 @@
+// Autenticación y permisos.
+//
+// Las peticiones que no son GET/HEAD/OPTIONS deben llevar una clave de API en
+// `Authorization: Bearer <clave>` o en `X-API-Key`, o bien el access token de un
+// usuario (`users.rs`) en `Authorization: Bearer`. De las claves solo se guarda
+// el hash SHA-256: la clave se muestra una única vez, al crearla desde la CLI
+// (`api-key create`). Las rutas de `/auth/` quedan fuera: son las que dan acceso.
+//
+// Usuarios y claves tienen un rol (viewer < contributor < editor < admin) y cada
+// handler declara el que necesita con el extractor `Authorized<require::...>`.
+
+use crate::models::{Role, api_key, user};
+use crate::problem::problem;
+use crate::users::JwtKeys;
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{Payload, ServiceRequest, ServiceResponse};
+use actix_web::error::InternalError;
+use actix_web::http::header::HeaderValue;
+use actix_web::http::{Method, StatusCode, header};
+use actix_web::middleware::Next;
+use actix_web::{Error, FromRequest, HttpMessage, HttpRequest, HttpResponse, web};
+use rand::RngCore;
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
+use sha2::{Digest, Sha256};
+use std::future::{Ready, ready};
+use std::marker::PhantomData;
+use utoipa::Modify;
+use utoipa::openapi::path::PathItemType;
+use utoipa::openapi::security::{
+    ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityRequirement, SecurityScheme,
+};
+use utoipa::openapi::{Components, OpenApi};
+
+/// Prefijo de las claves generadas, para reconocerlas a simple vista.
+const KEY_PREFIX: &str = "mk_";
+
+/// Caracteres de la clave que se guardan en claro (`prefix`) para listarlas.
+const VISIBLE_CHARS: usize = 10;
+
+pub const API_KEY_HEADER: &str = "X-API-Key";
+
+/// Rutas de login y registro, abiertas a todos.
+const PUBLIC_PREFIX: &str = "/auth/";
+
+/// Lecturas que exigen credenciales (las comprueba `Authorized`); en `ApiDoc` se
+/// documentan como las escrituras.
+const PROTECTED_READS: &[&str] = &["/trash", "/admin/audit"];
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn hash_key(key: &str) -> String {
+    hex(&Sha256::digest(key.as_bytes()))
+}
+
+/// `prefix` seguido de 32 bytes aleatorios en hexadecimal.
+pub fn random_token(prefix: &str) -> String {
+    let mut secret = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut secret);
+    format!("{}{}", prefix, hex(&secret))
+}
+
+/// Crea una clave nueva y devuelve la fila junto con la clave en claro.
+pub async fn create_key(
+    db: &DatabaseConnection,
+    name: &str,
+    role: Role,
+) -> Result<(api_key::Model, String), DbErr> {
+    let key = random_token(KEY_PREFIX);
+
+    let model = api_key::ActiveModel {
+        name: Set(name.to_string()),
+        prefix: Set(key[..VISIBLE_CHARS].to_string()),
+        key_hash: Set(hash_key(&key)),
+        created_at: Set(chrono::Utc::now()),
+        revoked_at: Set(None),
+        role: Set(role),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    Ok((model, key))
+}
+
+/// Revoca la clave `id`. Devuelve `false` si no existe o ya estaba revocada.
+pub async fn revoke_key(db: &DatabaseConnection, id: i32) -> Result<bool, DbErr> {
+    let result = api_key::Entity::update_many()
+        .col_expr(
+            api_key::Column::RevokedAt,
+            sea_orm::sea_query::Expr::value(chrono::Utc::now()),
+        )
+        .filter(api_key::Column::Id.eq(id))
+        .filter(api_key::Column::RevokedAt.is_null())
+        .exec(db)
+        .await?;
+    Ok(result.rows_affected > 0)
+}
+
+/// Clave enviada en `Authorization: Bearer` o en `X-API-Key`.
+fn presented_key(req: &ServiceRequest) -> Option<&str> {
+    let headers = req.headers();
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or_else(|| headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok()))
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+}
+
+/// Quién hace la petición (un usuario o una clave de API), con su rol.
+#[derive(Clone, Debug)]
+pub struct Principal {
+    /// `user:<id>` o `api-key:<id>`; es lo que se guarda como autor de los cambios.
+    pub actor: String,
+    pub role: Role,
+}
+
+tokio::task_local! {
+    /// Autor de la petición en curso, para `created_by`/`updated_by` (`models.rs`).
+    static ACTOR: Option<String>;
+}
+
+/// Autor de la petición en curso, si se ha identificado.
+pub fn current_actor() -> Option<String> {
+    ACTOR.try_with(Clone::clone).ok().flatten()
+}
+
+/// Ejecuta `future` con `actor` como autor de los cambios que haga (para la CLI).
+pub async fn with_actor<F: Future>(actor: &str, future: F) -> F::Output {
+    ACTOR.scope(Some(actor.to_string()), future).await
+}
+
+/// Identifica la clave de API o el access token presentado. El rol se lee de la
+/// base de datos en cada petición, así que un cambio de rol o una revocación se
+/// aplican de inmediato.
+async fn resolve(
+    db: &DatabaseConnection,
+    jwt: Option<&JwtKeys>,
+    key: &str,
+) -> Result<Option<Principal>, DbErr> {
+    if key.starts_with(KEY_PREFIX) {
+        let stored = api_key::Entity::find()
+            .filter(api_key::Column::KeyHash.eq(hash_key(key)))
+            .filter(api_key::Column::RevokedAt.is_null())
+            .one(db)
+            .await?;
+        return Ok(stored.map(|stored| Principal {
+            actor: format!("api-key:{}", stored.id),
+            role: stored.role,
+        }));
+    }
+
+    // No es una clave de API: debe ser el access token de un usuario.
+    let Some(claims) = jwt.and_then(|keys| keys.decode(key)) else {
+        return Ok(None);
+    };
+    let stored = user::Entity::find_by_id(claims.sub).one(db).await?;
+    Ok(stored.map(|stored| Principal {
+        actor: format!("user:{}", stored.id),
+        role: stored.role,
+    }))
+}
+
+/// Middleware: identifica al cliente si presenta credenciales y deja el
+/// `Principal` en las extensiones de la petición. Las lecturas y `/auth/` pasan
+/// también sin credenciales; el resto responde `401`. Los permisos de cada ruta
+/// los comprueba el extractor `Authorized`.
+pub async fn authenticate<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    let public = matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS)
+        || req.path().starts_with(PUBLIC_PREFIX);
+
+    let principal = match presented_key(&req) {
+        None => None,
+        Some(key) => {
+            let db = req
+                .app_data::<web::Data<DatabaseConnection>>()
+                .expect("DatabaseConnection not registered");
+            let jwt = req
+                .app_data::<web::Data<JwtKeys>>()
+                .map(|keys| keys.get_ref());
+            match resolve(db.get_ref(), jwt, key).await {
+                Ok(principal) => principal,
+                Err(e) => {
+                    return Ok(req
+                        .into_response(
+                            HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+                        )
+                        .map_into_right_body());
+                }
+            }
+        }
+    };
+
+    let actor = principal.as_ref().map(|principal| principal.actor.clone());
+    match principal {
+        Some(principal) => {
+            req.extensions_mut().insert(principal);
+        }
+        None if public => {}
+        None => {
+            let message = if presented_key(&req).is_some() {
+                "Invalid API key or access token"
+            } else {
+                "Missing API key or access token"
+            };
+            return Ok(req
+                .into_response(unauthorized(message))
+                .map_into_right_body());
+        }
+    }
+    ACTOR
+        .scope(actor, next.call(req))
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+fn unauthorized(message: &str) -> HttpResponse {
+    let mut response = problem(StatusCode::UNAUTHORIZED, message);
+    response
+        .headers_mut()
+        .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+    response
+}
+
+/// Rol mínimo exigido por `Authorized<R>`.
+pub trait RequiredRole {
+    const ROLE: Role;
+}
+
+/// Marcadores de rol para `Authorized`: `Authorized<require::Editor>`.
+pub mod require {
+    use super::{RequiredRole, Role};
+
+    pub struct Contributor;
+    pub struct Editor;
+    pub struct Admin;
+
+    impl RequiredRole for Contributor {
+        const ROLE: Role = Role::Contributor;
+    }
+    impl RequiredRole for Editor {
+        const ROLE: Role = Role::Editor;
+    }
+    impl RequiredRole for Admin {
+        const ROLE: Role = Role::Admin;
+    }
+}
+
+/// Extractor que exige el rol `R` o superior: `401` sin credenciales y `403`
+/// (problem+json) si el rol no alcanza.
+pub struct Authorized<R>(PhantomData<R>);
+
+impl<R: RequiredRole> FromRequest for Authorized<R> {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let principal = req.extensions().get::<Principal>().cloned();
+        let response = match principal {
+            Some(principal) if principal.role >= R::ROLE => {
+                return ready(Ok(Authorized(PhantomData)));
+            }
+            Some(principal) => problem(
+                StatusCode::FORBIDDEN,
+                format!(
+                    "Requires role {} (current role: {})",
+                    R::ROLE.as_str(),
+                    principal.role.as_str()
+                ),
+            ),
+            None => unauthorized("Missing API key or access token"),
+        };
+        ready(Err(InternalError::from_response("", response).into()))
+    }
+}
+
+/// Registra los esquemas `bearer_auth` (clave de API o access token) y `api_key`
+/// en `ApiDoc` y los exige en las operaciones de escritura, para que Swagger UI
+/// pueda enviar las credenciales.
+pub struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut OpenApi) {
+        let components = openapi.components.get_or_insert_with(Components::new);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new(API_KEY_HEADER))),
+        );
+
+        for (path, item) in openapi.paths.paths.iter_mut() {
+            if path.starts_with(PUBLIC_PREFIX) {
+                continue;
+            }
+            for (method, operation) in item.operations.iter_mut() {
+                if *method == PathItemType::Get && !PROTECTED_READS.contains(&path.as_str()) {
+                    continue;
+                }
+                // Basta con uno de los dos.
+                operation.security = Some(vec![
+                    SecurityRequirement::new("bearer_auth", Vec::<String>::new()),
+                    SecurityRequirement::new("api_key", Vec::<String>::new()),
+                ]);
+                operation
+                    .responses
+                    .responses
+                    .entry("401".to_string())
+                    .or_insert_with(|| {
+                        utoipa::openapi::Response::new("Faltan las credenciales o no son válidas")
+                            .into()
+                    });
+            }
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use crate::models::{actor, director, genre, movie};
+use crate::negotiate;
+use crate::search::escape_like;
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use sea_orm::sea_query::{Expr, LikeExpr, SimpleExpr};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, IdenStatic, QueryFilter, QueryOrder,
+    QuerySelect,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+const DEFAULT_LIMIT: u64 = 10;
+const MAX_LIMIT: u64 = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SuggestionKind {
+    Movie,
+    Actor,
+    Director,
+    Genre,
+}
+
+impl SuggestionKind {
+    const ALL: [SuggestionKind; 4] = [
+        SuggestionKind::Movie,
+        SuggestionKind::Actor,
+        SuggestionKind::Director,
+        SuggestionKind::Genre,
+    ];
+
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "movie" => Some(SuggestionKind::Movie),
+            "actor" => Some(SuggestionKind::Actor),
+            "director" => Some(SuggestionKind::Director),
+            "genre" => Some(SuggestionKind::Genre),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Suggestion {
+    pub id: i32,
+    pub kind: SuggestionKind,
+    pub label: String,
+    pub year: Option<i32>,
+}
+
+#[derive(Deserialize)]
+pub struct AutocompleteQuery {
+    q: Option<String>,
+    types: Option<String>,
+    limit: Option<u64>,
+}
+
+/// `column LIKE 'prefix%' ESCAPE '\'`, escapando los comodines que pueda traer
+/// el usuario. Con los índices `COLLATE NOCASE` SQLite lo resuelve por índice.
+fn prefix_filter<C: ColumnTrait>(column: C, prefix: &str) -> SimpleExpr {
+    let pattern = format!("{}%", escape_like(prefix));
+    Expr::col((column.entity_name(), column)).like(LikeExpr::new(pattern).escape('\\'))
+}
+
+async fn suggest(
+    db: &DatabaseConnection,
+    kind: SuggestionKind,
+    prefix: &str,
+    limit: u64,
+) -> Result<Vec<Suggestion>, DbErr> {
+    // Solo se leen las columnas que forman la sugerencia.
+    let rows: Vec<(i32, String, Option<i32>)> = match kind {
+        SuggestionKind::Movie => {
+            movie::Entity::find()
+                .select_only()
+                .columns([movie::Column::Id, movie::Column::Title, movie::Column::Year])
+                .filter(prefix_filter(movie::Column::Title, prefix))
+                .filter(movie::Column::DeletedAt.is_null())
+                .order_by_asc(movie::Column::Title)
+                .limit(limit)
+                .into_tuple()
+                .all(db)
+                .await?
+        }
+        SuggestionKind::Director => {
+            name_rows::<director::Entity, _>(
+                db,
+                director::Column::Id,
+                director::Column::Name,
+                director::Column::DeletedAt,
+                prefix,
+                limit,
+            )
+            .await?
+        }
+        SuggestionKind::Actor => {
+            name_rows::<actor::Entity, _>(
+                db,
+                actor::Column::Id,
+                actor::Column::Name,
+                actor::Column::DeletedAt,
+                prefix,
+                limit,
+            )
+            .await?
+        }
+        SuggestionKind::Genre => {
+            name_rows::<genre::Entity, _>(
+                db,
+                genre::Column::Id,
+                genre::Column::Name,
+                genre::Column::DeletedAt,
+                prefix,
+                limit,
+            )
+            .await?
+        }
+    };
+
+    Ok(rows
+        .into_iter()
+        .map(|(id, label, year)| Suggestion {
+            id,
+            kind,
+            label,
+            year,
+        })
+        .collect())
+}
+
+async fn name_rows<E, C>(
+    db: &DatabaseConnection,
+    id: C,
+    name: C,
+    deleted_at: C,
+    prefix: &str,
+    limit: u64,
+) -> Result<Vec<(i32, String, Option<i32>)>, DbErr>
+where
+    E: EntityTrait<Column = C>,
+    C: ColumnTrait + IdenStatic,
+{
+    let rows: Vec<(i32, String)> = E::find()
+        .select_only()
+        .columns([id, name])
+        .filter(prefix_filter(name, prefix))
+        .filter(deleted_at.is_null())
+        .order_by_asc(name)
+        .limit(limit)
+        .into_tuple()
+        .all(db)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(id, name)| (id, name, None))
+        .collect())
+}
+
+// GET /autocomplete
+#[utoipa::path(
+    get,
+    path = "/autocomplete",
+    responses(
+        (status = 200, description = "Sugerencias por prefijo", body = Vec<Suggestion>),
+        (status = 400, description = "Bad Request"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    params(
+        ("q" = String, Query, description = "Prefijo a buscar"),
+        ("types" = Option<String>, Query, description = "Tipos separados por comas: movie,actor,director,genre"),
+        ("limit" = Option<u64>, Query, description = "Número máximo de sugerencias (1-50)")
+    )
+)]
+pub async fn autocomplete(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    query: web::Query<AutocompleteQuery>,
+) -> impl Responder {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    let kinds = match query.types.as_deref().map(str::trim) {
+        None | Some("") => SuggestionKind::ALL.to_vec(),
+        Some(types) => {
+            let mut kinds = Vec::new();
+            for t in types.split(',').map(|t| t.trim().to_lowercase()) {
+                match SuggestionKind::parse(&t) {
+                    Some(kind) if !kinds.contains(&kind) => kinds.push(kind),
+                    Some(_) => {}
+                    None => return HttpResponse::BadRequest().body(format!("Unknown type: {}", t)),
+                }
+            }
+            kinds
+        }
+    };
+
+    let prefix = match query.q.as_deref().map(str::trim) {
+        Some(q) if !q.is_empty() => q,
+        _ => return negotiate::respond(&req, &Vec::<Suggestion>::new()),
+    };
+
+    let mut suggestions = Vec::new();
+    for kind in kinds {
+        match suggest(db.get_ref(), kind, prefix, limit).await {
+            Ok(found) => suggestions.extend(found),
+            Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+        }
+    }
+
+    // Las coincidencias más cortas primero: "Alien" antes que "Alien: Covenant".
+    suggestions.sort_by(|a, b| {
+        a.label
+            .len()
+            .cmp(&b.label.len())
+            .then_with(|| a.label.to_lowercase().cmp(&b.label.to_lowercase()))
+    });
+    suggestions.truncate(limit as usize);
+
+    negotiate::respond(&req, &suggestions)
+}
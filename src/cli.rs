@@ -0,0 +1,239 @@
+// Subcomandos de administración: `cargo run -- <comando> [opciones]`. Sin
+// argumentos, el binario arranca el servidor.
+
+use crate::auth;
+use crate::db::establish_connection;
+use crate::imdb::{self, ImdbOptions};
+use crate::models::{Role, api_key, user};
+use crate::trash;
+use chrono::{Duration, Utc};
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use std::io;
+use std::path::PathBuf;
+
+const USAGE: &str = "\
+Uso: movies-api-rest [comando]
+
+Sin comando arranca el servidor HTTP.
+
+Comandos:
+  import-imdb [--dir RUTA] [--title-types movie,tvMovie] [--dry-run]
+      Importa title.basics, title.crew, title.principals y name.basics
+      (.tsv.gz) desde RUTA (por defecto, el directorio actual).
+  api-key create NOMBRE [--role ROL]
+      Crea una clave de API para las rutas de escritura y la muestra (solo
+      esta vez). ROL: viewer, contributor, editor (por defecto) o admin.
+  api-key list
+      Lista las claves (id, nombre, prefijo, rol, estado).
+  api-key revoke ID
+      Revoca la clave ID.
+  user list
+      Lista los usuarios con su rol.
+  user set-role EMAIL ROL
+      Cambia el rol de un usuario (p. ej. para crear el primer admin).
+  trash purge [--older-than-days N]
+      Borra definitivamente lo que lleva en la papelera más de N días (por
+      defecto, 30).
+  help
+      Muestra esta ayuda.";
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("{}\n\n{}", message, USAGE),
+    )
+}
+
+/// Valor de la opción `--name` que está en `args[*i]`, avanzando el índice.
+fn option_value(args: &[String], i: &mut usize) -> io::Result<String> {
+    let name = &args[*i];
+    *i += 1;
+    args.get(*i)
+        .cloned()
+        .ok_or_else(|| invalid(format!("missing value for {}", name)))
+}
+
+pub async fn run(args: &[String]) -> io::Result<()> {
+    match args[0].as_str() {
+        "import-imdb" => {
+            let mut options = ImdbOptions {
+                dir: PathBuf::from("."),
+                title_types: vec!["movie".to_string()],
+                dry_run: false,
+            };
+            let mut i = 1;
+            while i < args.len() {
+                match args[i].as_str() {
+                    "--dir" => options.dir = PathBuf::from(option_value(args, &mut i)?),
+                    "--title-types" => {
+                        options.title_types = option_value(args, &mut i)?
+                            .split(',')
+                            .map(|t| t.trim().to_string())
+                            .filter(|t| !t.is_empty())
+                            .collect();
+                    }
+                    "--dry-run" => options.dry_run = true,
+                    other => return Err(invalid(format!("unknown option: {}", other))),
+                }
+                i += 1;
+            }
+            let db = establish_connection().await;
+            let summary = auth::with_actor("cli:import-imdb", imdb::run(&db, &options)).await?;
+            println!(
+                "{}: {} creadas, {} ya importadas, {} con errores.",
+                if options.dry_run {
+                    "Prueba terminada (no se ha guardado nada)"
+                } else {
+                    "Importación terminada"
+                },
+                summary.created,
+                summary.matched,
+                summary.failed
+            );
+            Ok(())
+        }
+        "api-key" => api_key_command(&args[1..]).await,
+        "user" => user_command(&args[1..]).await,
+        "trash" => trash_command(&args[1..]).await,
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        other => Err(invalid(format!("unknown command: {}", other))),
+    }
+}
+
+async fn api_key_command(args: &[String]) -> io::Result<()> {
+    let db = establish_connection().await;
+    match args {
+        [action, name, rest @ ..] if action == "create" => {
+            let role = match rest {
+                [] => Role::Editor,
+                [flag, role] if flag == "--role" => parse_role(role)?,
+                _ => return Err(invalid(format!("invalid options: {}", rest.join(" ")))),
+            };
+            let (model, key) = auth::create_key(&db, name, role)
+                .await
+                .map_err(io::Error::other)?;
+            println!("Clave {} ({}) creada:\n\n  {}\n", model.id, model.name, key);
+            println!("Guárdala ahora: no se puede volver a mostrar.");
+            Ok(())
+        }
+        [action] if action == "list" => {
+            let keys = api_key::Entity::find()
+                .order_by_asc(api_key::Column::Id)
+                .all(&db)
+                .await
+                .map_err(io::Error::other)?;
+            for key in keys {
+                let status = match key.revoked_at {
+                    Some(at) => format!("revocada {}", at.format("%Y-%m-%d %H:%M")),
+                    None => "activa".to_string(),
+                };
+                println!(
+                    "{}\t{}\t{}…\t{}\tcreada {}\t{}",
+                    key.id,
+                    key.name,
+                    key.prefix,
+                    key.role.as_str(),
+                    key.created_at.format("%Y-%m-%d %H:%M"),
+                    status
+                );
+            }
+            Ok(())
+        }
+        [action, id] if action == "revoke" => {
+            let id: i32 = id
+                .parse()
+                .map_err(|_| invalid(format!("invalid key id: {}", id)))?;
+            if auth::revoke_key(&db, id).await.map_err(io::Error::other)? {
+                println!("Clave {} revocada.", id);
+                Ok(())
+            } else {
+                Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no active key with id {}", id),
+                ))
+            }
+        }
+        _ => Err(invalid(format!(
+            "invalid api-key command: {}",
+            args.join(" ")
+        ))),
+    }
+}
+
+fn parse_role(role: &str) -> io::Result<Role> {
+    Role::parse(role).ok_or_else(|| invalid(format!("unknown role: {}", role)))
+}
+
+async fn user_command(args: &[String]) -> io::Result<()> {
+    let db = establish_connection().await;
+    match args {
+        [action] if action == "list" => {
+            let users = user::Entity::find()
+                .order_by_asc(user::Column::Id)
+                .all(&db)
+                .await
+                .map_err(io::Error::other)?;
+            for user in users {
+                println!("{}\t{}\t{}", user.id, user.email, user.role.as_str());
+            }
+            Ok(())
+        }
+        [action, email, role] if action == "set-role" => {
+            let role = parse_role(role)?;
+            let Some(found) = user::Entity::find()
+                .filter(user::Column::Email.eq(email.trim().to_lowercase()))
+                .one(&db)
+                .await
+                .map_err(io::Error::other)?
+            else {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no user with email {}", email),
+                ));
+            };
+            let mut active: user::ActiveModel = found.into();
+            active.role = Set(role);
+            active.update(&db).await.map_err(io::Error::other)?;
+            println!("{} ahora es {}.", email, role.as_str());
+            Ok(())
+        }
+        _ => Err(invalid(format!("invalid user command: {}", args.join(" ")))),
+    }
+}
+
+async fn trash_command(args: &[String]) -> io::Result<()> {
+    let days = match args {
+        [action] if action == "purge" => trash::DEFAULT_RETENTION_DAYS,
+        [action, flag, days] if action == "purge" && flag == "--older-than-days" => days
+            .parse()
+            .ok()
+            .filter(|days: &i64| *days >= 0)
+            .ok_or_else(|| invalid(format!("invalid number of days: {}", days)))?,
+        _ => {
+            return Err(invalid(format!(
+                "invalid trash command: {}",
+                args.join(" ")
+            )));
+        }
+    };
+    let db = establish_connection().await;
+    let cutoff = Utc::now() - Duration::days(days);
+    let report = auth::with_actor("cli:trash-purge", trash::purge(&db, cutoff))
+        .await
+        .map_err(io::Error::other)?;
+    println!(
+        "Borrados definitivamente (más de {} días en la papelera): {} películas, {} directores, {} actores, {} géneros.",
+        days, report.movies, report.directors, report.actors, report.genres
+    );
+    if report.directors_kept > 0 {
+        println!(
+            "{} directores se conservan porque los referencian películas de la papelera.",
+            report.directors_kept
+        );
+    }
+    Ok(())
+}
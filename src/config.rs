@@ -0,0 +1,158 @@
+// Configuración de la aplicación, leída de variables de entorno (o de `.env`).
+// Los valores que no se pueden interpretar se avisan por stderr y se sustituyen
+// por el valor por defecto.
+
+use std::str::FromStr;
+
+#[derive(Clone)]
+pub struct AppConfig {
+    pub rate_limit: RateLimitConfig,
+    pub cors: CorsConfig,
+    pub log: LogConfig,
+    pub otel: OtelConfig,
+    pub shutdown: ShutdownConfig,
+}
+
+/// Límites del token bucket de cada cliente. Cada petición consume una ficha
+/// (o `search_cost` en las rutas de búsqueda); el bucket se rellena a `per_second`
+/// fichas por segundo hasta `burst`.
+#[derive(Clone)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    pub read_burst: u32,
+    pub read_per_second: f64,
+    pub write_burst: u32,
+    pub write_per_second: f64,
+    pub search_cost: u32,
+}
+
+/// Política CORS. En las listas, `*` significa «cualquiera»; los orígenes admiten
+/// además comodines (`https://*.example.com`).
+#[derive(Clone)]
+pub struct CorsConfig {
+    pub allowed_origins: Vec<String>,
+    /// Orígenes admitidos en las escrituras; si no se define, los de `allowed_origins`.
+    pub write_allowed_origins: Option<Vec<String>>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    pub allow_credentials: bool,
+    pub max_age: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Texto legible, para desarrollo.
+    Pretty,
+    /// Un objeto JSON por línea, para agregadores de logs.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "pretty" | "text" => Ok(LogFormat::Pretty),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct LogConfig {
+    pub format: LogFormat,
+    /// Directivas de `tracing_subscriber::EnvFilter` (`info,sea_orm::database=trace`).
+    pub filter: String,
+}
+
+/// Exportación de trazas por OTLP/HTTP. Desactivada si no hay `endpoint`.
+#[derive(Clone)]
+pub struct OtelConfig {
+    /// URL base del colector (`http://localhost:4318`); se añade `/v1/traces`.
+    pub endpoint: Option<String>,
+    pub service_name: String,
+}
+
+/// Apagado ordenado (`shutdown.rs`).
+#[derive(Clone)]
+pub struct ShutdownConfig {
+    /// Segundos que `/readyz` falla antes de dejar de aceptar conexiones.
+    pub drain_seconds: u64,
+    /// Segundos que tienen las peticiones en curso para terminar.
+    pub timeout_seconds: u64,
+}
+
+impl AppConfig {
+    pub fn from_env() -> Self {
+        AppConfig {
+            rate_limit: RateLimitConfig {
+                enabled: env_or("RATE_LIMIT_ENABLED", true),
+                read_burst: env_or("RATE_LIMIT_READ_BURST", 120),
+                read_per_second: env_or("RATE_LIMIT_READ_PER_SECOND", 10.0),
+                write_burst: env_or("RATE_LIMIT_WRITE_BURST", 30),
+                write_per_second: env_or("RATE_LIMIT_WRITE_PER_SECOND", 1.0),
+                search_cost: env_or("RATE_LIMIT_SEARCH_COST", 5),
+            },
+            cors: CorsConfig {
+                allowed_origins: env_list("CORS_ALLOWED_ORIGINS").unwrap_or_default(),
+                write_allowed_origins: env_list("CORS_WRITE_ALLOWED_ORIGINS"),
+                allowed_methods: env_list("CORS_ALLOWED_METHODS").unwrap_or_else(|| {
+                    strings(&["GET", "HEAD", "OPTIONS", "POST", "PUT", "DELETE"])
+                }),
+                allowed_headers: env_list("CORS_ALLOWED_HEADERS").unwrap_or_else(|| {
+                    strings(&["Accept", "Authorization", "Content-Type", "X-API-Key"])
+                }),
+                allow_credentials: env_or("CORS_ALLOW_CREDENTIALS", false),
+                max_age: env_or("CORS_MAX_AGE", 3600),
+            },
+            log: LogConfig {
+                format: env_or("LOG_FORMAT", LogFormat::Pretty),
+                filter: env_or(
+                    "RUST_LOG",
+                    "info,sqlx=warn,sea_orm::database=trace".to_string(),
+                ),
+            },
+            otel: OtelConfig {
+                endpoint: std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+                    .ok()
+                    .filter(|endpoint| !endpoint.trim().is_empty()),
+                service_name: env_or("OTEL_SERVICE_NAME", "movies-api-rest".to_string()),
+            },
+            shutdown: ShutdownConfig {
+                drain_seconds: env_or("SHUTDOWN_DRAIN_SECONDS", 0),
+                timeout_seconds: env_or("SHUTDOWN_TIMEOUT_SECONDS", 30),
+            },
+        }
+    }
+}
+
+fn env_or<T: FromStr>(name: &str, default: T) -> T {
+    match std::env::var(name) {
+        Ok(value) if !value.trim().is_empty() => match value.trim().parse() {
+            Ok(parsed) => parsed,
+            Err(_) => {
+                eprintln!("{}: invalid value {:?}; using the default", name, value);
+                default
+            }
+        },
+        _ => default,
+    }
+}
+
+/// Lista separada por comas; `None` si la variable no está definida.
+fn env_list(name: &str) -> Option<Vec<String>> {
+    let value = std::env::var(name).ok()?;
+    Some(
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(String::from)
+            .collect(),
+    )
+}
+
+fn strings(items: &[&str]) -> Vec<String> {
+    items.iter().map(|item| item.to_string()).collect()
+}
@@ -0,0 +1,147 @@
+// Política CORS construida a partir de `CorsConfig`.
+//
+// Sin `CORS_ALLOWED_ORIGINS` no se admite ningún origen cruzado. Las escrituras
+// (y sus preflight) pueden restringirse a otra lista con
+// `CORS_WRITE_ALLOWED_ORIGINS`.
+
+use crate::config::CorsConfig;
+use actix_cors::Cors;
+use actix_web::dev::RequestHead;
+use actix_web::http::Method;
+use actix_web::http::header::{self, HeaderName, HeaderValue};
+
+/// Cabeceras de respuesta que el navegador deja leer al código JavaScript.
+const EXPOSED_HEADERS: &[&str] = &[
+    "ratelimit-limit",
+    "ratelimit-remaining",
+    "ratelimit-reset",
+    "retry-after",
+    "www-authenticate",
+    "x-request-id",
+];
+
+fn is_any(list: &[String]) -> bool {
+    list.iter().any(|item| item == "*")
+}
+
+/// Compara un origen con un patrón en el que `*` sustituye a cualquier texto.
+fn origin_matches(pattern: &str, origin: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = origin.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // Sin comodines: debe coincidir entero.
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+fn allowed(list: &[String], origin: &str) -> bool {
+    list.iter()
+        .any(|pattern| pattern == "*" || origin_matches(pattern, origin))
+}
+
+/// Método de la petición o, en un preflight, el de la petición que anuncia.
+fn is_write(head: &RequestHead) -> bool {
+    let method = if head.method == Method::OPTIONS {
+        head.headers()
+            .get(header::ACCESS_CONTROL_REQUEST_METHOD)
+            .and_then(|v| Method::from_bytes(v.as_bytes()).ok())
+            .unwrap_or(Method::OPTIONS)
+    } else {
+        head.method.clone()
+    };
+    !matches!(method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+pub fn build(config: &CorsConfig) -> Cors {
+    let mut cors = Cors::default().max_age(config.max_age);
+
+    let read = config.allowed_origins.clone();
+    let write = config.write_allowed_origins.clone();
+    cors = match &write {
+        None if is_any(&read) => cors.allow_any_origin(),
+        _ => cors.allowed_origin_fn(move |origin: &HeaderValue, head: &RequestHead| {
+            let Ok(origin) = origin.to_str() else {
+                return false;
+            };
+            match &write {
+                Some(write) if is_write(head) => allowed(write, origin),
+                _ => allowed(&read, origin),
+            }
+        }),
+    };
+
+    if is_any(&config.allowed_methods) {
+        cors = cors.allow_any_method();
+    } else {
+        let methods: Vec<Method> = config
+            .allowed_methods
+            .iter()
+            .filter_map(
+                |name| match Method::from_bytes(name.to_uppercase().as_bytes()) {
+                    Ok(method) => Some(method),
+                    Err(_) => {
+                        tracing::warn!("CORS_ALLOWED_METHODS: ignoring invalid method {:?}", name);
+                        None
+                    }
+                },
+            )
+            .collect();
+        cors = cors.allowed_methods(methods);
+    }
+
+    if is_any(&config.allowed_headers) {
+        cors = cors.allow_any_header();
+    } else {
+        let headers: Vec<HeaderName> = config
+            .allowed_headers
+            .iter()
+            .filter_map(|name| match HeaderName::from_bytes(name.as_bytes()) {
+                Ok(header) => Some(header),
+                Err(_) => {
+                    tracing::warn!("CORS_ALLOWED_HEADERS: ignoring invalid header {:?}", name);
+                    None
+                }
+            })
+            .collect();
+        cors = cors.allowed_headers(headers);
+    }
+
+    if config.allow_credentials {
+        cors = cors.supports_credentials();
+    }
+    cors.expose_headers(EXPOSED_HEADERS.iter().copied())
+}
+
+/// Avisa al arrancar si la política deja pasar cualquier origen.
+pub fn warn_if_permissive(config: &CorsConfig) {
+    let any_origin = is_any(&config.allowed_origins)
+        || config.write_allowed_origins.as_deref().is_some_and(is_any);
+    if !any_origin {
+        return;
+    }
+    if is_any(&config.allowed_methods) && is_any(&config.allowed_headers) {
+        tracing::warn!(
+            "CORS is fully permissive (any origin, method and header); \
+             set CORS_ALLOWED_ORIGINS to restrict it"
+        );
+    } else {
+        tracing::warn!("CORS allows any origin; set CORS_ALLOWED_ORIGINS to restrict it");
+    }
+    if config.allow_credentials {
+        tracing::warn!(
+            "CORS allows credentials from any origin; any website can send \
+             authenticated requests with the user's cookies"
+        );
+    }
+}
@@ -0,0 +1,549 @@
+// Exportación e importación CSV de películas, directores, actores y géneros.
+//
+// En las películas, las listas de actores y géneros van en una sola celda
+// separadas por `|`. La importación es todo o nada: si alguna fila no es válida
+// no se guarda ninguna y se devuelve la lista de errores por fila.
+
+use crate::audit::{self, Audited};
+use crate::auth::{Authorized, require};
+use crate::export::movies_after;
+use crate::models::{actor, director, genre};
+use crate::route_handler::{
+    CreateActor, CreateDirector, CreateGenre, CreateMovie, MovieFull, ValidationError,
+};
+use actix_multipart::Multipart;
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::web::Bytes;
+use actix_web::{HttpResponse, HttpResponseBuilder, Responder, web};
+use futures_util::{StreamExt, stream};
+use sea_orm::ActiveValue::Set;
+use sea_orm::{
+    ActiveModelBehavior, ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait,
+    IntoActiveModel, QueryFilter, QueryOrder, TransactionTrait,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// Separador de los valores múltiples dentro de una celda.
+const LIST_SEPARATOR: char = '|';
+
+/// Tamaño máximo del fichero CSV subido.
+pub const CSV_MAX_BYTES: usize = 16 * 1024 * 1024;
+
+#[derive(Serialize, ToSchema)]
+pub struct CsvRowError {
+    /// Línea del fichero (la cabecera es la 1).
+    pub row: u64,
+    pub error: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CsvImportReport {
+    pub imported: usize,
+    pub errors: Vec<CsvRowError>,
+}
+
+/// Fila de `movies/export.csv`. Al importar se usan `title`, `year`,
+/// `director_id`, `actor_ids` y `genre_ids`; el resto es informativo.
+#[derive(Serialize, Deserialize)]
+struct MovieRow {
+    #[serde(default)]
+    id: Option<i32>,
+    title: String,
+    year: Option<i32>,
+    director_id: i32,
+    #[serde(default)]
+    director: String,
+    #[serde(default)]
+    actor_ids: String,
+    #[serde(default)]
+    actors: String,
+    #[serde(default)]
+    genre_ids: String,
+    #[serde(default)]
+    genres: String,
+}
+
+fn join<T: ToString>(values: impl Iterator<Item = T>) -> String {
+    values
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(&LIST_SEPARATOR.to_string())
+}
+
+/// Ids de una celda, sin repetidos y en el orden en que aparecen.
+fn split_ids(cell: &str, column: &str) -> Result<Vec<i32>, String> {
+    let mut ids = Vec::new();
+    for s in cell
+        .split(LIST_SEPARATOR)
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+    {
+        let id = s
+            .parse()
+            .map_err(|_| format!("invalid id '{}' in column {}", s, column))?;
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    Ok(ids)
+}
+
+impl MovieRow {
+    fn into_create(self) -> Result<CreateMovie, String> {
+        Ok(CreateMovie {
+            title: self.title,
+            director_id: self.director_id,
+            year: self.year,
+            actor_ids: split_ids(&self.actor_ids, "actor_ids")?,
+            genre_ids: split_ids(&self.genre_ids, "genre_ids")?,
+        })
+    }
+}
+
+impl From<MovieFull> for MovieRow {
+    fn from(m: MovieFull) -> Self {
+        MovieRow {
+            id: Some(m.id),
+            title: m.title,
+            year: m.year,
+            director_id: m.director.as_ref().map_or(0, |d| d.id),
+            director: m.director.map(|d| d.name).unwrap_or_default(),
+            actor_ids: join(m.actors.iter().map(|a| a.id)),
+            actors: join(m.actors.iter().map(|a| &a.name)),
+            genre_ids: join(m.genres.iter().map(|g| g.id)),
+            genres: join(m.genres.iter().map(|g| &g.name)),
+        }
+    }
+}
+
+/// `200` con `Content-Type` CSV y `filename` como nombre del fichero descargado.
+fn csv_ok(filename: &str) -> HttpResponseBuilder {
+    let mut builder = HttpResponse::Ok();
+    builder
+        .content_type("text/csv; charset=utf-8")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(filename.to_string())],
+        });
+    builder
+}
+
+fn csv_response<T: Serialize>(filename: &str, rows: &[T]) -> HttpResponse {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for row in rows {
+        if let Err(e) = writer.serialize(row) {
+            return HttpResponse::InternalServerError().body(format!("Error: {}", e));
+        }
+    }
+    let body = match writer.into_inner() {
+        Ok(body) => body,
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    };
+    csv_ok(filename).body(body)
+}
+
+/// Lee el primer fichero del formulario multipart.
+async fn read_upload(mut payload: Multipart) -> Result<Vec<u8>, String> {
+    while let Some(field) = payload.next().await {
+        let mut field = field.map_err(|e| format!("Invalid multipart body: {}", e))?;
+        let is_file = field
+            .content_disposition()
+            .is_some_and(|cd| cd.get_filename().is_some());
+        if !is_file {
+            continue;
+        }
+        let mut data = Vec::new();
+        while let Some(chunk) = field.next().await {
+            let chunk = chunk.map_err(|e| format!("Invalid multipart body: {}", e))?;
+            if data.len() + chunk.len() > CSV_MAX_BYTES {
+                return Err(format!("CSV file exceeds {} bytes", CSV_MAX_BYTES));
+            }
+            data.extend_from_slice(&chunk);
+        }
+        return Ok(data);
+    }
+    Err("Missing CSV file in multipart body".to_string())
+}
+
+/// Interpreta cada fila como `T`; devuelve las filas válidas con su línea y los
+/// errores de las demás.
+fn parse_rows<T: DeserializeOwned>(data: &[u8]) -> (Vec<(u64, T)>, Vec<CsvRowError>) {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(data);
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    let headers = match reader.headers() {
+        Ok(headers) => headers.clone(),
+        Err(e) => {
+            errors.push(CsvRowError {
+                row: 1,
+                error: e.to_string(),
+            });
+            return (rows, errors);
+        }
+    };
+    for result in reader.records() {
+        let record = match result {
+            Ok(record) => record,
+            Err(e) => {
+                errors.push(CsvRowError {
+                    row: e.position().map_or(0, |p| p.line()),
+                    error: e.to_string(),
+                });
+                continue;
+            }
+        };
+        let row = record.position().map_or(0, |p| p.line());
+        match record.deserialize::<T>(Some(&headers)) {
+            Ok(value) => rows.push((row, value)),
+            Err(e) => errors.push(CsvRowError {
+                row,
+                error: e.to_string(),
+            }),
+        }
+    }
+    (rows, errors)
+}
+
+fn import_response(report: CsvImportReport) -> HttpResponse {
+    if report.errors.is_empty() {
+        HttpResponse::Created().json(report)
+    } else {
+        HttpResponse::BadRequest().json(report)
+    }
+}
+
+//
+// --- Movies ---
+//
+
+/// Filas CSV del siguiente lote de películas con id mayor que `after`, con la
+/// cabecera delante si `header`. Devuelve `None` cuando no quedan más.
+async fn next_movies_csv(
+    db: &DatabaseConnection,
+    after: i32,
+    header: bool,
+) -> Result<Option<(Bytes, i32)>, DbErr> {
+    let movies = movies_after(db, after).await?;
+    let Some(last) = movies.last().map(|m| m.id) else {
+        return Ok(None);
+    };
+
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(header)
+        .from_writer(Vec::new());
+    for movie in movies {
+        writer
+            .serialize(MovieRow::from(movie))
+            .map_err(|e| DbErr::Custom(format!("Serialization error: {}", e)))?;
+    }
+    let chunk = writer
+        .into_inner()
+        .map_err(|e| DbErr::Custom(format!("Serialization error: {}", e)))?;
+    Ok(Some((Bytes::from(chunk), last)))
+}
+
+// GET /movies/export.csv
+#[utoipa::path(
+    get,
+    path = "/movies/export.csv",
+    responses(
+        (status = 200, description = "Películas en CSV, con actores y géneros separados por '|'", content_type = "text/csv"),
+        (status = 500, description = "Internal Server Error")
+    )
+)]
+pub async fn export_movies_csv(db: web::Data<DatabaseConnection>) -> impl Responder {
+    // Como `/export`, por lotes ordenados por id para no cargar todo el catálogo.
+    let db: Arc<DatabaseConnection> = db.into_inner();
+    // Estado: id de la última película enviada y si falta la cabecera, o `None`
+    // al terminar.
+    let body = stream::unfold(Some((0, true)), move |state| {
+        let db = db.clone();
+        async move {
+            let (after, header) = state?;
+            match next_movies_csv(&db, after, header).await {
+                Ok(Some((chunk, last))) => Some((Ok(chunk), Some((last, false)))),
+                Ok(None) => None,
+                Err(e) => Some((Err(actix_web::error::ErrorInternalServerError(e)), None)),
+            }
+        }
+    });
+    csv_ok("movies.csv").streaming(body)
+}
+
+// POST /movies/import
+#[utoipa::path(
+    post,
+    path = "/movies/import",
+    request_body(content = String, content_type = "multipart/form-data", description = "Fichero CSV con columnas title, year, director_id, actor_ids y genre_ids (ids separados por '|')"),
+    responses(
+        (status = 201, description = "Todas las filas importadas", body = CsvImportReport),
+        (status = 400, description = "Errores por fila; no se ha guardado nada", body = CsvImportReport),
+        (status = 403, description = "Requiere rol editor", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error")
+    )
+)]
+pub async fn import_movies_csv(
+    _auth: Authorized<require::Editor>,
+    db: web::Data<DatabaseConnection>,
+    payload: Multipart,
+) -> impl Responder {
+    let data = match read_upload(payload).await {
+        Ok(data) => data,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    let (rows, mut errors) = parse_rows::<MovieRow>(&data);
+
+    let txn = match db.begin().await {
+        Ok(txn) => txn,
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    };
+
+    let mut movies = Vec::with_capacity(rows.len());
+    for (row, movie) in rows {
+        let movie = match movie.into_create() {
+            Ok(movie) => movie,
+            Err(error) => {
+                errors.push(CsvRowError { row, error });
+                continue;
+            }
+        };
+        match movie.validate(&txn).await {
+            Ok(()) => movies.push(movie),
+            Err(ValidationError::Invalid(error)) => errors.push(CsvRowError { row, error }),
+            Err(ValidationError::Db(e)) => {
+                return HttpResponse::InternalServerError().body(format!("DB error: {}", e));
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        errors.sort_by_key(|e| e.row);
+        return import_response(CsvImportReport {
+            imported: 0,
+            errors,
+        });
+    }
+
+    for movie in &movies {
+        if let Err(e) = movie.insert(&txn).await {
+            return HttpResponse::InternalServerError().body(format!("Error: {}", e));
+        }
+    }
+    if let Err(e) = txn.commit().await {
+        return HttpResponse::InternalServerError().body(format!("Error: {}", e));
+    }
+
+    import_response(CsvImportReport {
+        imported: movies.len(),
+        errors,
+    })
+}
+
+//
+// --- Directors, actors and genres ---
+//
+
+/// Exporta las filas no borradas; `deleted_at` es la columna del borrado lógico.
+async fn export_named<E>(
+    db: &DatabaseConnection,
+    order: E::Column,
+    deleted_at: E::Column,
+    filename: &str,
+) -> HttpResponse
+where
+    E: EntityTrait,
+    E::Model: Serialize,
+{
+    match E::find()
+        .filter(deleted_at.is_null())
+        .order_by_asc(order)
+        .all(db)
+        .await
+    {
+        Ok(rows) => csv_response(filename, &rows),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
+
+/// Importa filas con una única columna obligatoria, `name`, como hacen
+/// `CreateDirector`, `CreateActor` y `CreateGenre`.
+async fn import_named<F, A>(
+    db: &DatabaseConnection,
+    payload: Multipart,
+    to_model: impl Fn(F) -> A,
+) -> HttpResponse
+where
+    F: DeserializeOwned,
+    A: ActiveModelTrait + ActiveModelBehavior + Send,
+    <A::Entity as EntityTrait>::Model: IntoActiveModel<A> + Audited,
+{
+    let data = match read_upload(payload).await {
+        Ok(data) => data,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    let (rows, errors) = parse_rows::<F>(&data);
+    if !errors.is_empty() {
+        return import_response(CsvImportReport {
+            imported: 0,
+            errors,
+        });
+    }
+
+    let imported = rows.len();
+    let result: Result<(), DbErr> = async {
+        let txn = db.begin().await?;
+        for (_, row) in rows {
+            audit::insert(&txn, to_model(row)).await?;
+        }
+        txn.commit().await
+    }
+    .await;
+
+    match result {
+        Ok(()) => import_response(CsvImportReport {
+            imported,
+            errors: Vec::new(),
+        }),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
+
+// GET /directors/export.csv
+#[utoipa::path(
+    get,
+    path = "/directors/export.csv",
+    responses(
+        (status = 200, description = "Directores en CSV", content_type = "text/csv"),
+        (status = 500, description = "Internal Server Error")
+    )
+)]
+pub async fn export_directors_csv(db: web::Data<DatabaseConnection>) -> impl Responder {
+    export_named::<director::Entity>(
+        db.get_ref(),
+        director::Column::Id,
+        director::Column::DeletedAt,
+        "directors.csv",
+    )
+    .await
+}
+
+// POST /directors/import
+#[utoipa::path(
+    post,
+    path = "/directors/import",
+    request_body(content = String, content_type = "multipart/form-data", description = "Fichero CSV con columna name"),
+    responses(
+        (status = 201, description = "Todas las filas importadas", body = CsvImportReport),
+        (status = 400, description = "Errores por fila; no se ha guardado nada", body = CsvImportReport),
+        (status = 403, description = "Requiere rol editor", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error")
+    )
+)]
+pub async fn import_directors_csv(
+    _auth: Authorized<require::Editor>,
+    db: web::Data<DatabaseConnection>,
+    payload: Multipart,
+) -> impl Responder {
+    import_named(db.get_ref(), payload, |form: CreateDirector| {
+        director::ActiveModel {
+            name: Set(form.name),
+            ..Default::default()
+        }
+    })
+    .await
+}
+
+// GET /actors/export.csv
+#[utoipa::path(
+    get,
+    path = "/actors/export.csv",
+    responses(
+        (status = 200, description = "Actores en CSV", content_type = "text/csv"),
+        (status = 500, description = "Internal Server Error")
+    )
+)]
+pub async fn export_actors_csv(db: web::Data<DatabaseConnection>) -> impl Responder {
+    export_named::<actor::Entity>(
+        db.get_ref(),
+        actor::Column::Id,
+        actor::Column::DeletedAt,
+        "actors.csv",
+    )
+    .await
+}
+
+// POST /actors/import
+#[utoipa::path(
+    post,
+    path = "/actors/import",
+    request_body(content = String, content_type = "multipart/form-data", description = "Fichero CSV con columna name"),
+    responses(
+        (status = 201, description = "Todas las filas importadas", body = CsvImportReport),
+        (status = 400, description = "Errores por fila; no se ha guardado nada", body = CsvImportReport),
+        (status = 403, description = "Requiere rol editor", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error")
+    )
+)]
+pub async fn import_actors_csv(
+    _auth: Authorized<require::Editor>,
+    db: web::Data<DatabaseConnection>,
+    payload: Multipart,
+) -> impl Responder {
+    import_named(db.get_ref(), payload, |form: CreateActor| {
+        actor::ActiveModel {
+            name: Set(form.name),
+            ..Default::default()
+        }
+    })
+    .await
+}
+
+// GET /genres/export.csv
+#[utoipa::path(
+    get,
+    path = "/genres/export.csv",
+    responses(
+        (status = 200, description = "Géneros en CSV", content_type = "text/csv"),
+        (status = 500, description = "Internal Server Error")
+    )
+)]
+pub async fn export_genres_csv(db: web::Data<DatabaseConnection>) -> impl Responder {
+    export_named::<genre::Entity>(
+        db.get_ref(),
+        genre::Column::Id,
+        genre::Column::DeletedAt,
+        "genres.csv",
+    )
+    .await
+}
+
+// POST /genres/import
+#[utoipa::path(
+    post,
+    path = "/genres/import",
+    request_body(content = String, content_type = "multipart/form-data", description = "Fichero CSV con columna name"),
+    responses(
+        (status = 201, description = "Todas las filas importadas", body = CsvImportReport),
+        (status = 400, description = "Errores por fila; no se ha guardado nada", body = CsvImportReport),
+        (status = 403, description = "Requiere rol editor", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error")
+    )
+)]
+pub async fn import_genres_csv(
+    _auth: Authorized<require::Editor>,
+    db: web::Data<DatabaseConnection>,
+    payload: Multipart,
+) -> impl Responder {
+    import_named(db.get_ref(), payload, |form: CreateGenre| {
+        genre::ActiveModel {
+            name: Set(form.name),
+            ..Default::default()
+        }
+    })
+    .await
+}
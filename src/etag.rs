@@ -0,0 +1,160 @@
+// ETags y peticiones condicionales.
+//
+// Las fichas de directores, actores y géneros tienen como versión su `version`; la
+// de una película combina su versión con las de su director, actores y géneros,
+// ya que la ficha los incluye. Los `PUT` y `DELETE` respetan `If-Match` (`412` si
+// la fila ha cambiado) y los `GET` de fichas `If-None-Match` (`304` si no ha
+// cambiado).
+//
+// Una ficha se sirve en JSON, XML, JSON-LD… según `Accept`, así que el ETag es
+// fuerte y propio de cada representación: la versión y el formato (`"3-json"`,
+// `"3-xml"`). `If-Match` solo compara la versión: cualquier representación de la
+// versión vigente sirve para modificarla.
+
+use crate::problem::problem;
+use crate::route_handler::MovieFull;
+use actix_web::http::StatusCode;
+use actix_web::http::header::{self, ETag, EntityTag, HeaderValue, IfMatch, IfNoneMatch};
+use actix_web::{HttpMessage, HttpRequest, HttpResponse};
+use sea_orm::{DbErr, TransactionError};
+use sha2::{Digest, Sha256};
+
+/// Versión de una fila con `version`, para su ETag.
+pub fn version_tag(version: i32) -> String {
+    version.to_string()
+}
+
+/// Versión de la ficha de una película, para su ETag: su versión y un resumen de
+/// las versiones de lo que incluye, para que renombrar un actor también cambie la
+/// ficha. Actores y géneros se ordenan por id, así que no depende del orden en
+/// que se hayan cargado.
+pub fn movie_tag(movie: &MovieFull) -> String {
+    let mut actors: Vec<(i32, i32)> = movie.actors.iter().map(|a| (a.id, a.version)).collect();
+    actors.sort_unstable();
+    let mut genres: Vec<(i32, i32)> = movie.genres.iter().map(|g| (g.id, g.version)).collect();
+    genres.sort_unstable();
+
+    let mut hasher = Sha256::new();
+    if let Some(director) = &movie.director {
+        hasher.update(format!("d{}:{};", director.id, director.version));
+    }
+    for (id, version) in actors {
+        hasher.update(format!("a{}:{};", id, version));
+    }
+    for (id, version) in genres {
+        hasher.update(format!("g{}:{};", id, version));
+    }
+    let digest: String = hasher.finalize()[..8]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("{}-{}", movie.version, digest)
+}
+
+/// ETag de la representación de `version` en `format` (`json`, `xml`,
+/// `ld+json`…).
+fn representation_tag(version: &str, format: &str) -> EntityTag {
+    EntityTag::new_strong(format!("{}-{}", version, format))
+}
+
+/// Formato de `response` según su `Content-Type`: el subtipo, sin parámetros.
+fn response_format(response: &HttpResponse) -> String {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|content_type| content_type.split(';').next())
+        .and_then(|media| media.trim().split_once('/'))
+        .map(|(_, subtype)| subtype.to_ascii_lowercase())
+        .unwrap_or_else(|| "json".to_string())
+}
+
+/// Versión de un ETag de `representation_tag`. Un ETag débil nunca cumple
+/// `If-Match` (RFC 9110, 13.1.1).
+fn tag_version(tag: &EntityTag) -> Option<&str> {
+    if tag.weak {
+        return None;
+    }
+    tag.tag().rsplit_once('-').map(|(version, _)| version)
+}
+
+/// `Err` con la respuesta `412` si la petición trae un `If-Match` sin ningún ETag
+/// de la versión `current`. Sin `If-Match` la petición se acepta.
+pub fn check_if_match(req: &HttpRequest, current: &str) -> Result<(), HttpResponse> {
+    match req.get_header::<IfMatch>() {
+        None | Some(IfMatch::Any) => Ok(()),
+        Some(IfMatch::Items(tags)) if tags.iter().any(|tag| tag_version(tag) == Some(current)) => {
+            Ok(())
+        }
+        Some(IfMatch::Items(_)) => Err(precondition_failed(current)),
+    }
+}
+
+/// `true` si el `If-None-Match` de la petición coincide con `current`.
+fn not_modified(req: &HttpRequest, current: &EntityTag) -> bool {
+    match req.get_header::<IfNoneMatch>() {
+        None => false,
+        Some(IfNoneMatch::Any) => true,
+        Some(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(current)),
+    }
+}
+
+/// Respuesta al `GET` de una ficha en la versión `version`: `304` si el cliente ya
+/// tiene esta misma representación; si no, `response` con su ETag.
+pub fn conditional_get(req: &HttpRequest, version: &str, response: HttpResponse) -> HttpResponse {
+    if !response.status().is_success() {
+        return response;
+    }
+    let tag = representation_tag(version, &response_format(&response));
+    if not_modified(req, &tag) {
+        return set_tag(HttpResponse::NotModified().finish(), tag);
+    }
+    set_tag(response, tag)
+}
+
+/// Añade a `response` el ETag de su representación de `version`.
+pub fn with_tag(response: HttpResponse, version: &str) -> HttpResponse {
+    let tag = representation_tag(version, &response_format(&response));
+    set_tag(response, tag)
+}
+
+/// Respuesta `412` con el ETag vigente (el de JSON), para que el cliente sepa a
+/// qué versión debe actualizar su copia.
+pub fn precondition_failed(current: &str) -> HttpResponse {
+    let tag = representation_tag(current, "json");
+    set_tag(
+        problem(
+            StatusCode::PRECONDITION_FAILED,
+            format!(
+                "The resource has changed (current ETag {}); fetch it again and retry",
+                tag
+            ),
+        ),
+        tag,
+    )
+}
+
+/// Respuesta `412` cuando la fila cambió entre la lectura y la escritura.
+pub fn changed_concurrently() -> HttpResponse {
+    problem(
+        StatusCode::PRECONDITION_FAILED,
+        "The resource was modified by another request; fetch it again and retry",
+    )
+}
+
+/// Añade `ETag` a `response`. Las fichas pueden servirse en varios formatos, así
+/// que también `Vary: Accept`.
+fn set_tag(mut response: HttpResponse, tag: EntityTag) -> HttpResponse {
+    let headers = response.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(&ETag(tag).to_string()) {
+        headers.insert(header::ETAG, value);
+    }
+    headers.insert(header::VARY, HeaderValue::from_static("Accept"));
+    response
+}
+
+/// `true` si la escritura no se hizo porque la fila había cambiado de versión (ver
+/// `audit::update`).
+pub fn is_stale(e: &TransactionError<DbErr>) -> bool {
+    matches!(e, TransactionError::Transaction(DbErr::RecordNotUpdated))
+}
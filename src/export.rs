@@ -0,0 +1,95 @@
+// Volcado completo del catálogo en NDJSON: una película (con director, actores y
+// géneros) por línea. Se lee por lotes ordenados por id y cada lote se envía
+// como un fragmento de la respuesta, así que la memoria no crece con el catálogo.
+// Las películas de la papelera no se incluyen.
+
+use crate::models::movie;
+use crate::route_handler::{MovieFull, load_movies_full};
+use actix_web::web::Bytes;
+use actix_web::{HttpResponse, Responder, web};
+use futures_util::stream;
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// Películas leídas por consulta.
+const EXPORT_BATCH_SIZE: u64 = 500;
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    format: Option<String>,
+}
+
+/// Siguiente lote de películas no borradas con id mayor que `after`, en orden de
+/// id; vacío cuando no quedan más.
+pub async fn movies_after(db: &DatabaseConnection, after: i32) -> Result<Vec<MovieFull>, DbErr> {
+    let movies = movie::Entity::find()
+        .filter(movie::Column::Id.gt(after))
+        .filter(movie::Column::DeletedAt.is_null())
+        .order_by_asc(movie::Column::Id)
+        .limit(EXPORT_BATCH_SIZE)
+        .all(db)
+        .await?;
+    load_movies_full(db, movies).await
+}
+
+/// Siguiente lote de películas con id mayor que `after`, ya serializado.
+/// Devuelve `None` cuando no quedan más.
+async fn next_batch(db: &DatabaseConnection, after: i32) -> Result<Option<(Bytes, i32)>, DbErr> {
+    let movies = movies_after(db, after).await?;
+    let Some(last) = movies.last().map(|m| m.id) else {
+        return Ok(None);
+    };
+
+    let mut chunk = Vec::new();
+    for movie in movies {
+        serde_json::to_writer(&mut chunk, &movie)
+            .map_err(|e| DbErr::Custom(format!("Serialization error: {}", e)))?;
+        chunk.push(b'\n');
+    }
+    Ok(Some((Bytes::from(chunk), last)))
+}
+
+// GET /export
+#[utoipa::path(
+    get,
+    path = "/export",
+    responses(
+        (status = 200, description = "Catálogo completo, una película (MovieFull) por línea", content_type = "application/x-ndjson"),
+        (status = 400, description = "Formato no soportado")
+    ),
+    params(
+        ("format" = Option<String>, Query, description = "Formato del volcado; solo `ndjson` (por defecto)")
+    )
+)]
+pub async fn export_catalog(
+    db: web::Data<DatabaseConnection>,
+    query: web::Query<ExportQuery>,
+) -> impl Responder {
+    match query.format.as_deref() {
+        None | Some("ndjson") => {}
+        Some(other) => {
+            return HttpResponse::BadRequest().body(format!("Unsupported format: {}", other));
+        }
+    }
+
+    let db: Arc<DatabaseConnection> = db.into_inner();
+    // Estado: id de la última película enviada, o `None` al terminar.
+    let body = stream::unfold(Some(0), move |after| {
+        let db = db.clone();
+        async move {
+            match next_batch(&db, after?).await {
+                Ok(Some((chunk, last))) => Some((Ok(chunk), Some(last))),
+                Ok(None) => None,
+                // La cabecera ya se ha enviado: solo queda cortar la respuesta.
+                Err(e) => Some((Err(actix_web::error::ErrorInternalServerError(e)), None)),
+            }
+        }
+    });
+
+    HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(body)
+}
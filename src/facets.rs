@@ -0,0 +1,231 @@
+// Facetas de /movies/full: recuentos por género, director, actor y década sobre
+// todas las películas que cumplen la búsqueda, no solo las de la página actual.
+
+use crate::models::{actor, director, genre, movie, movie_actor, movie_genre};
+use sea_orm::sea_query::{Alias, Expr, SelectStatement};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+    QueryTrait, Select,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+/// Número máximo de valores devueltos en las facetas de género, director y actor.
+const FACET_LIMIT: u64 = 10;
+
+/// Alias de la columna con el recuento en las consultas agrupadas.
+const COUNT: &str = "count";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FacetKind {
+    Genre,
+    Director,
+    Actor,
+    Decade,
+}
+
+impl FacetKind {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "genre" => Some(FacetKind::Genre),
+            "director" => Some(FacetKind::Director),
+            "actor" => Some(FacetKind::Actor),
+            "decade" => Some(FacetKind::Decade),
+            _ => None,
+        }
+    }
+}
+
+/// Interpreta `facets=genre,director,...`; devuelve el nombre desconocido si lo hay.
+pub fn parse_kinds(facets: &str) -> Result<Vec<FacetKind>, String> {
+    let mut kinds = Vec::new();
+    for name in facets
+        .split(',')
+        .map(|f| f.trim().to_lowercase())
+        .filter(|f| !f.is_empty())
+    {
+        match FacetKind::parse(&name) {
+            Some(kind) if !kinds.contains(&kind) => kinds.push(kind),
+            Some(_) => {}
+            None => return Err(name),
+        }
+    }
+    Ok(kinds)
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct FacetBucket {
+    /// Id del género/director/actor, o primer año de la década.
+    pub value: i32,
+    pub label: String,
+    pub count: u64,
+}
+
+#[derive(Default, Serialize, ToSchema)]
+pub struct Facets {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub genre: Option<Vec<FacetBucket>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub director: Option<Vec<FacetBucket>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actor: Option<Vec<FacetBucket>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decade: Option<Vec<FacetBucket>>,
+}
+
+/// Calcula las facetas pedidas para las películas de `movies` (ya filtrada).
+pub async fn compute(
+    db: &DatabaseConnection,
+    movies: &Select<movie::Entity>,
+    kinds: &[FacetKind],
+) -> Result<Facets, DbErr> {
+    let movie_ids = movies
+        .clone()
+        .select_only()
+        .column(movie::Column::Id)
+        .into_query();
+
+    let mut facets = Facets::default();
+    for kind in kinds {
+        match kind {
+            FacetKind::Genre => {
+                let counts = top_counts::<movie_genre::Entity>(
+                    db,
+                    movie_genre::Column::GenreId,
+                    movie_genre::Column::MovieId,
+                    movie_ids.clone(),
+                    live_ids::<genre::Entity>(genre::Column::Id, genre::Column::DeletedAt),
+                )
+                .await?;
+                let names =
+                    labels::<genre::Entity>(db, genre::Column::Id, genre::Column::Name, &counts)
+                        .await?;
+                facets.genre = Some(buckets(counts, names));
+            }
+            FacetKind::Director => {
+                let counts = top_counts::<movie::Entity>(
+                    db,
+                    movie::Column::DirectorId,
+                    movie::Column::Id,
+                    movie_ids.clone(),
+                    live_ids::<director::Entity>(director::Column::Id, director::Column::DeletedAt),
+                )
+                .await?;
+                let names = labels::<director::Entity>(
+                    db,
+                    director::Column::Id,
+                    director::Column::Name,
+                    &counts,
+                )
+                .await?;
+                facets.director = Some(buckets(counts, names));
+            }
+            FacetKind::Actor => {
+                let counts = top_counts::<movie_actor::Entity>(
+                    db,
+                    movie_actor::Column::ActorId,
+                    movie_actor::Column::MovieId,
+                    movie_ids.clone(),
+                    live_ids::<actor::Entity>(actor::Column::Id, actor::Column::DeletedAt),
+                )
+                .await?;
+                let names =
+                    labels::<actor::Entity>(db, actor::Column::Id, actor::Column::Name, &counts)
+                        .await?;
+                facets.actor = Some(buckets(counts, names));
+            }
+            FacetKind::Decade => {
+                facets.decade = Some(decades(db, movie_ids.clone()).await?);
+            }
+        }
+    }
+    Ok(facets)
+}
+
+/// Ids de las filas de `E` que no están en la papelera.
+fn live_ids<E: EntityTrait>(id: E::Column, deleted_at: E::Column) -> SelectStatement {
+    E::find()
+        .select_only()
+        .column(id)
+        .filter(deleted_at.is_null())
+        .into_query()
+}
+
+/// Los `FACET_LIMIT` valores de `group` más frecuentes entre las filas cuyo
+/// `movie_id` está en `movie_ids`, contando solo los valores de `live_ids`.
+async fn top_counts<E: EntityTrait>(
+    db: &DatabaseConnection,
+    group: E::Column,
+    movie_id: E::Column,
+    movie_ids: SelectStatement,
+    live_ids: SelectStatement,
+) -> Result<Vec<(i32, i64)>, DbErr> {
+    E::find()
+        .select_only()
+        .column(group)
+        .column_as(movie_id.count(), COUNT)
+        .filter(movie_id.in_subquery(movie_ids))
+        .filter(group.in_subquery(live_ids))
+        .group_by(group)
+        .order_by_desc(Expr::col(Alias::new(COUNT)))
+        .order_by_asc(group)
+        .limit(FACET_LIMIT)
+        .into_tuple()
+        .all(db)
+        .await
+}
+
+async fn labels<E: EntityTrait>(
+    db: &DatabaseConnection,
+    id: E::Column,
+    name: E::Column,
+    counts: &[(i32, i64)],
+) -> Result<HashMap<i32, String>, DbErr> {
+    let rows: Vec<(i32, String)> = E::find()
+        .select_only()
+        .columns([id, name])
+        .filter(id.is_in(counts.iter().map(|(id, _)| *id)))
+        .into_tuple()
+        .all(db)
+        .await?;
+    Ok(rows.into_iter().collect())
+}
+
+fn buckets(counts: Vec<(i32, i64)>, mut names: HashMap<i32, String>) -> Vec<FacetBucket> {
+    counts
+        .into_iter()
+        .map(|(id, count)| FacetBucket {
+            value: id,
+            label: names.remove(&id).unwrap_or_default(),
+            count: count as u64,
+        })
+        .collect()
+}
+
+async fn decades(
+    db: &DatabaseConnection,
+    movie_ids: SelectStatement,
+) -> Result<Vec<FacetBucket>, DbErr> {
+    // División entera: 1987 / 10 * 10 = 1980.
+    let decade = Expr::col(movie::Column::Year).div(10).mul(10);
+    let counts: Vec<(i32, i64)> = movie::Entity::find()
+        .select_only()
+        .column_as(decade.clone(), "decade")
+        .column_as(movie::Column::Id.count(), COUNT)
+        .filter(movie::Column::Id.in_subquery(movie_ids))
+        .filter(movie::Column::Year.is_not_null())
+        .group_by(decade.clone())
+        .order_by_asc(decade)
+        .into_tuple()
+        .all(db)
+        .await?;
+    Ok(counts
+        .into_iter()
+        .map(|(decade, count)| FacetBucket {
+            value: decade,
+            label: format!("{}s", decade),
+            count: count as u64,
+        })
+        .collect())
+}
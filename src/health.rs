@@ -0,0 +1,150 @@
+// Sondas para el orquestador.
+//
+// `/healthz` solo indica que el proceso responde. `/readyz` comprueba además que la
+// base de datos contesta y que todas las migraciones de `Migrator` están aplicadas;
+// si algo falla responde `503` para que no se le envíe tráfico. Durante el apagado
+// (`shutdown.rs`) responde `503` aunque todo lo demás esté bien.
+
+use crate::shutdown::Lifecycle;
+use actix_web::{HttpResponse, Responder, web};
+use migration::{Migrator, MigratorTrait};
+use sea_orm::DatabaseConnection;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Ok,
+    Error,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Liveness {
+    pub status: HealthStatus,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ComponentStatus {
+    pub status: HealthStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Migraciones pendientes (solo en `migrations`).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pending: Vec<String>,
+}
+
+impl ComponentStatus {
+    fn ok() -> Self {
+        ComponentStatus {
+            status: HealthStatus::Ok,
+            error: None,
+            pending: Vec::new(),
+        }
+    }
+
+    fn error(error: impl ToString) -> Self {
+        ComponentStatus {
+            status: HealthStatus::Error,
+            error: Some(error.to_string()),
+            pending: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ReadinessComponents {
+    /// `error` mientras el servidor se está apagando.
+    pub server: ComponentStatus,
+    pub database: ComponentStatus,
+    pub migrations: ComponentStatus,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Readiness {
+    pub status: HealthStatus,
+    pub components: ReadinessComponents,
+}
+
+/// El proceso está vivo
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    responses(
+        (status = 200, description = "El proceso responde", body = Liveness)
+    ),
+    tag = "health"
+)]
+pub async fn healthz() -> impl Responder {
+    HttpResponse::Ok().json(Liveness {
+        status: HealthStatus::Ok,
+    })
+}
+
+async fn check_database(db: &DatabaseConnection) -> ComponentStatus {
+    match db.ping().await {
+        Ok(()) => ComponentStatus::ok(),
+        Err(e) => ComponentStatus::error(e),
+    }
+}
+
+async fn check_migrations(db: &DatabaseConnection) -> ComponentStatus {
+    match Migrator::get_pending_migrations(db).await {
+        Ok(pending) if pending.is_empty() => ComponentStatus::ok(),
+        Ok(pending) => ComponentStatus {
+            status: HealthStatus::Error,
+            error: Some(format!("{} pending migrations", pending.len())),
+            pending: pending.iter().map(|m| m.name().to_string()).collect(),
+        },
+        Err(e) => ComponentStatus::error(e),
+    }
+}
+
+/// El servicio puede recibir tráfico
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    responses(
+        (status = 200, description = "Base de datos disponible y migraciones aplicadas", body = Readiness),
+        (status = 503, description = "Algún componente no está listo o el servidor se está apagando", body = Readiness)
+    ),
+    tag = "health"
+)]
+pub async fn readyz(
+    db: web::Data<DatabaseConnection>,
+    lifecycle: web::Data<Lifecycle>,
+) -> impl Responder {
+    let server = if lifecycle.is_draining() {
+        ComponentStatus::error("shutting down")
+    } else {
+        ComponentStatus::ok()
+    };
+    let database = check_database(db.get_ref()).await;
+    // Sin base de datos no tiene sentido consultar las migraciones.
+    let migrations = if database.status == HealthStatus::Ok {
+        check_migrations(db.get_ref()).await
+    } else {
+        ComponentStatus::error("database unavailable")
+    };
+
+    let ready = [&server, &database, &migrations]
+        .iter()
+        .all(|component| component.status == HealthStatus::Ok);
+    let body = Readiness {
+        status: if ready {
+            HealthStatus::Ok
+        } else {
+            HealthStatus::Error
+        },
+        components: ReadinessComponents {
+            server,
+            database,
+            migrations,
+        },
+    };
+    if ready {
+        HttpResponse::Ok().json(body)
+    } else {
+        HttpResponse::ServiceUnavailable().json(body)
+    }
+}
@@ -0,0 +1,209 @@
+// Importador de los ficheros TSV no comerciales de IMDb
+// (https://developer.imdb.com/non-commercial-datasets/):
+//
+//   title.basics.tsv.gz      -> películas, año y géneros
+//   title.crew.tsv.gz        -> director (el primero de la lista)
+//   title.principals.tsv.gz  -> actores (categorías actor/actress)
+//   name.basics.tsv.gz       -> nombres de directores y actores
+//
+// Los títulos se convierten en registros de `import::run` con los `tconst`/`nconst`
+// como `external_id`, así que volver a lanzarlo salta lo ya importado y continúa
+// donde se quedó.
+
+use crate::import::{self, EntityRef, ImportMovie, ImportStatus, ImportSummary, ParsedRecord};
+use flate2::read::MultiGzDecoder;
+use sea_orm::DatabaseConnection;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+/// Títulos enviados a `import::run` en cada llamada.
+const IMDB_CHUNK_SIZE: usize = 5_000;
+
+/// Valor nulo en los ficheros de IMDb.
+const NULL: &str = "\\N";
+
+pub struct ImdbOptions {
+    pub dir: PathBuf,
+    pub title_types: Vec<String>,
+    pub dry_run: bool,
+}
+
+struct Title {
+    title: String,
+    year: Option<i32>,
+    genres: Vec<String>,
+}
+
+fn non_null(value: &str) -> Option<&str> {
+    Some(value).filter(|v| *v != NULL && !v.is_empty())
+}
+
+/// Busca `name.tsv.gz` (o `name.tsv`) en `dir`.
+fn dataset_path(dir: &Path, name: &str) -> io::Result<PathBuf> {
+    [format!("{}.tsv.gz", name), format!("{}.tsv", name)]
+        .into_iter()
+        .map(|file| dir.join(file))
+        .find(|path| path.exists())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{}.tsv.gz not found in {}", name, dir.display()),
+            )
+        })
+}
+
+/// Recorre las filas (sin la cabecera) de un TSV de IMDb, comprimido o no.
+/// Los ficheros no usan comillas, así que basta con partir por tabuladores.
+fn for_each_row(path: &Path, mut row: impl FnMut(&[&str])) -> io::Result<()> {
+    let file = File::open(path)?;
+    let reader: Box<dyn BufRead> = if path.extension().is_some_and(|ext| ext == "gz") {
+        Box::new(BufReader::new(MultiGzDecoder::new(file)))
+    } else {
+        Box::new(BufReader::new(file))
+    };
+    for line in reader.lines().skip(1) {
+        let line = line?;
+        let columns: Vec<&str> = line.split('\t').collect();
+        row(&columns);
+    }
+    Ok(())
+}
+
+/// Importa los ficheros de `options.dir`. El progreso y los títulos que fallan
+/// van al log; devuelve el resumen de toda la importación.
+pub async fn run(db: &DatabaseConnection, options: &ImdbOptions) -> io::Result<ImportSummary> {
+    let dir = options.dir.as_path();
+
+    // title.basics: tconst, titleType, primaryTitle, originalTitle, isAdult,
+    // startYear, endYear, runtimeMinutes, genres
+    let mut titles: HashMap<String, Title> = HashMap::new();
+    for_each_row(&dataset_path(dir, "title.basics")?, |row| {
+        if row.len() < 9 || !options.title_types.iter().any(|t| t == row[1]) {
+            return;
+        }
+        titles.insert(
+            row[0].to_string(),
+            Title {
+                title: row[2].to_string(),
+                year: non_null(row[5]).and_then(|y| y.parse().ok()),
+                genres: non_null(row[8])
+                    .map(|g| g.split(',').map(str::to_string).collect())
+                    .unwrap_or_default(),
+            },
+        );
+    })?;
+    tracing::info!(
+        "{} titles of type {}",
+        titles.len(),
+        options.title_types.join(",")
+    );
+
+    // title.crew: tconst, directors, writers
+    let mut directors: HashMap<String, String> = HashMap::new();
+    for_each_row(&dataset_path(dir, "title.crew")?, |row| {
+        if row.len() < 2 || !titles.contains_key(row[0]) {
+            return;
+        }
+        if let Some(first) = non_null(row[1]).and_then(|d| d.split(',').next()) {
+            directors.insert(row[0].to_string(), first.to_string());
+        }
+    })?;
+    // `movies.director_id` es obligatorio: sin director no hay película.
+    titles.retain(|tconst, _| directors.contains_key(tconst));
+    tracing::info!("{} titles with a director", titles.len());
+
+    // title.principals: tconst, ordering, nconst, category, job, characters
+    let mut cast: HashMap<String, Vec<String>> = HashMap::new();
+    for_each_row(&dataset_path(dir, "title.principals")?, |row| {
+        if row.len() < 4 || !matches!(row[3], "actor" | "actress") || !titles.contains_key(row[0]) {
+            return;
+        }
+        cast.entry(row[0].to_string())
+            .or_default()
+            .push(row[2].to_string());
+    })?;
+
+    // name.basics: nconst, primaryName, ... (solo los que se van a usar)
+    let wanted: HashSet<&str> = directors
+        .values()
+        .chain(cast.values().flatten())
+        .map(String::as_str)
+        .collect();
+    let mut names: HashMap<String, String> = HashMap::new();
+    for_each_row(&dataset_path(dir, "name.basics")?, |row| {
+        if row.len() >= 2 && wanted.contains(row[0]) {
+            names.insert(row[0].to_string(), row[1].to_string());
+        }
+    })?;
+    tracing::info!("{} people referenced", names.len());
+
+    let person = |nconst: &str| EntityRef::Full {
+        name: names.get(nconst).cloned(),
+        external_id: Some(nconst.to_string()),
+    };
+
+    // Orden estable para que una importación interrumpida se reanude igual.
+    let mut tconsts: Vec<String> = titles.keys().cloned().collect();
+    tconsts.sort();
+
+    let total = tconsts.len();
+    let mut summary = ImportSummary::default();
+    for (chunk_index, chunk) in tconsts.chunks(IMDB_CHUNK_SIZE).enumerate() {
+        let records: Vec<ParsedRecord> = chunk
+            .iter()
+            .enumerate()
+            .map(|(i, tconst)| {
+                let title = &titles[tconst];
+                let record = ImportMovie {
+                    title: title.title.clone(),
+                    year: title.year,
+                    external_id: Some(tconst.clone()),
+                    director: person(&directors[tconst]),
+                    actors: cast
+                        .get(tconst)
+                        .into_iter()
+                        .flatten()
+                        // Un actor sin nombre no se puede crear; se omite.
+                        .filter(|nconst| names.contains_key(*nconst))
+                        .map(|nconst| person(nconst))
+                        .collect(),
+                    genres: title
+                        .genres
+                        .iter()
+                        .map(|g| EntityRef::Name(g.clone()))
+                        .collect(),
+                };
+                (chunk_index * IMDB_CHUNK_SIZE + i + 1, Ok(record))
+            })
+            .collect();
+
+        let report = import::run(db, records, options.dry_run)
+            .await
+            .map_err(io::Error::other)?;
+        for record in &report.records {
+            if record.status == ImportStatus::Failed {
+                tracing::warn!(
+                    "{} ({}) failed: {}",
+                    chunk[record.record - chunk_index * IMDB_CHUNK_SIZE - 1],
+                    record.title.as_deref().unwrap_or_default(),
+                    record.error.as_deref().unwrap_or_default()
+                );
+            }
+        }
+        summary.created += report.summary.created;
+        summary.matched += report.summary.matched;
+        summary.failed += report.summary.failed;
+        tracing::info!(
+            "{}/{} titles (created {}, already imported {}, failed {})",
+            (chunk_index * IMDB_CHUNK_SIZE + chunk.len()),
+            total,
+            summary.created,
+            summary.matched,
+            summary.failed
+        );
+    }
+
+    Ok(summary)
+}
@@ -0,0 +1,570 @@
+// Importación masiva de películas con sus directores, actores y géneros.
+//
+// Las referencias se resuelven por `external_id` o por nombre y se crean si no
+// existen; las que están en la papelera no se reutilizan. Los registros se
+// insertan en transacciones de `IMPORT_BATCH_SIZE`; cada registro va en su propio
+// savepoint, así que un registro erróneo no invalida el resto del lote.
+
+use crate::audit::{self, Audited};
+use crate::auth::{Authorized, require};
+use crate::jsonld;
+use crate::models::{RevisionAction, actor, director, genre, movie, movie_actor, movie_genre};
+use crate::revisions;
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use sea_orm::ActiveValue::Set;
+use sea_orm::prelude::DateTimeUtc;
+use sea_orm::{
+    ActiveModelBehavior, ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr,
+    EntityTrait, IntoActiveModel, ModelTrait, QueryFilter, QueryOrder, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Registros por transacción.
+const IMPORT_BATCH_SIZE: usize = 500;
+
+/// Tamaño máximo del cuerpo aceptado por `POST /import`.
+pub const IMPORT_MAX_BYTES: usize = 64 * 1024 * 1024;
+
+/// Director, actor o género: un nombre (`"Ridley Scott"`) o un objeto con
+/// `name` y/o `external_id`.
+#[derive(Clone, Debug, Deserialize, ToSchema)]
+#[serde(untagged)]
+pub enum EntityRef {
+    Name(String),
+    Full {
+        name: Option<String>,
+        external_id: Option<String>,
+    },
+}
+
+impl EntityRef {
+    /// `(name, external_id)` sin espacios sobrantes; las cadenas vacías cuentan
+    /// como ausentes.
+    fn parts(&self) -> (Option<&str>, Option<&str>) {
+        match self {
+            EntityRef::Name(name) => (non_empty(Some(name)), None),
+            EntityRef::Full { name, external_id } => {
+                (non_empty(name.as_ref()), non_empty(external_id.as_ref()))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ImportMovie {
+    pub title: String,
+    pub year: Option<i32>,
+    pub external_id: Option<String>,
+    pub director: EntityRef,
+    #[serde(default)]
+    pub actors: Vec<EntityRef>,
+    #[serde(default)]
+    pub genres: Vec<EntityRef>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportStatus {
+    Created,
+    Matched,
+    Failed,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RefKind {
+    Director,
+    Actor,
+    Genre,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ReferenceReport {
+    pub kind: RefKind,
+    pub id: i32,
+    pub name: String,
+    pub status: ImportStatus,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RecordReport {
+    /// Posición del registro en el documento (1 = primero; en NDJSON, la línea).
+    pub record: usize,
+    pub status: ImportStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub movie_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub references: Vec<ReferenceReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl RecordReport {
+    fn failed(record: usize, title: Option<String>, error: String) -> Self {
+        RecordReport {
+            record,
+            status: ImportStatus::Failed,
+            movie_id: None,
+            title,
+            references: Vec::new(),
+            error: Some(error),
+        }
+    }
+}
+
+#[derive(Default, Serialize, ToSchema)]
+pub struct ImportSummary {
+    pub created: usize,
+    pub matched: usize,
+    pub failed: usize,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ImportReport {
+    /// Si es `true` no se ha guardado nada; los ids son los que se habrían asignado.
+    pub dry_run: bool,
+    pub summary: ImportSummary,
+    pub records: Vec<RecordReport>,
+}
+
+/// Registro ya leído del documento (`Err` si no se pudo interpretar) junto a su
+/// posición.
+pub type ParsedRecord = (usize, Result<ImportMovie, String>);
+
+/// Importa los registros y devuelve el informe. Solo falla si la base de datos
+/// no permite abrir o cerrar una transacción; los errores de cada registro van
+/// al informe.
+pub async fn run(
+    db: &DatabaseConnection,
+    records: Vec<ParsedRecord>,
+    dry_run: bool,
+) -> Result<ImportReport, DbErr> {
+    // En modo de prueba cada lote se deshace al terminar, en lugar de ir todo en
+    // una transacción que bloquearía al resto de escrituras durante la importación
+    // entera. Un registro ve lo que "crearon" los anteriores de su lote, pero no
+    // los de lotes anteriores.
+    let mut reports = Vec::with_capacity(records.len());
+    let mut records = records.into_iter().peekable();
+    while records.peek().is_some() {
+        let txn = db.begin().await?;
+        for (position, record) in records.by_ref().take(IMPORT_BATCH_SIZE) {
+            let report = match record {
+                Err(e) => RecordReport::failed(position, None, e),
+                Ok(record) => {
+                    let savepoint = txn.begin().await?;
+                    match import_movie(&savepoint, position, &record).await {
+                        Ok(report) => {
+                            savepoint.commit().await?;
+                            report
+                        }
+                        Err(e) => {
+                            savepoint.rollback().await?;
+                            RecordReport::failed(position, Some(record.title.clone()), e)
+                        }
+                    }
+                }
+            };
+            reports.push(report);
+        }
+        if dry_run {
+            txn.rollback().await?;
+        } else {
+            txn.commit().await?;
+        }
+    }
+
+    let mut summary = ImportSummary::default();
+    for report in &reports {
+        match report.status {
+            ImportStatus::Created => summary.created += 1,
+            ImportStatus::Matched => summary.matched += 1,
+            ImportStatus::Failed => summary.failed += 1,
+        }
+    }
+
+    Ok(ImportReport {
+        dry_run,
+        summary,
+        records: reports,
+    })
+}
+
+fn non_empty(s: Option<&String>) -> Option<&str> {
+    s.map(|s| s.trim()).filter(|s| !s.is_empty())
+}
+
+fn db_error(e: DbErr) -> String {
+    format!("DB error: {}", e)
+}
+
+async fn import_movie<C: ConnectionTrait>(
+    db: &C,
+    position: usize,
+    record: &ImportMovie,
+) -> Result<RecordReport, String> {
+    let title = record.title.trim();
+    if title.is_empty() {
+        return Err("title must not be empty".to_string());
+    }
+    let external_id = non_empty(record.external_id.as_ref());
+
+    let matched = |model: movie::Model, references| RecordReport {
+        record: position,
+        status: ImportStatus::Matched,
+        movie_id: Some(model.id),
+        title: Some(model.title),
+        references,
+        error: None,
+    };
+
+    if let Some(external_id) = external_id {
+        let existing = movie::Entity::find()
+            .filter(movie::Column::ExternalId.eq(external_id))
+            .one(db)
+            .await
+            .map_err(db_error)?;
+        match existing {
+            // El external_id es único también en la papelera: no se puede crear otra.
+            Some(existing) if existing.deleted_at.is_some() => {
+                return Err(trashed_error("movie", external_id));
+            }
+            Some(existing) => return Ok(matched(existing, Vec::new())),
+            None => {}
+        }
+    }
+
+    let mut references = Vec::new();
+    let director = resolve(db, RefKind::Director, &record.director).await?;
+    let director_id = director.id;
+    references.push(director);
+
+    // Sin external_id, la misma película es el mismo título, año y director.
+    if external_id.is_none() {
+        let existing = movie::Entity::find()
+            .filter(movie::Column::Title.eq(title))
+            .filter(match record.year {
+                Some(year) => movie::Column::Year.eq(year),
+                None => movie::Column::Year.is_null(),
+            })
+            .filter(movie::Column::DirectorId.eq(director_id))
+            .filter(movie::Column::DeletedAt.is_null())
+            .order_by_asc(movie::Column::Id)
+            .one(db)
+            .await
+            .map_err(db_error)?;
+        if let Some(existing) = existing {
+            return Ok(matched(existing, references));
+        }
+    }
+
+    let created = audit::insert(
+        db,
+        movie::ActiveModel {
+            title: Set(title.to_string()),
+            director_id: Set(director_id),
+            year: Set(record.year),
+            external_id: Set(external_id.map(str::to_string)),
+            ..Default::default()
+        },
+    )
+    .await
+    .map_err(db_error)?;
+
+    let mut actor_ids = Vec::new();
+    for actor in &record.actors {
+        let reference = resolve(db, RefKind::Actor, actor).await?;
+        if !actor_ids.contains(&reference.id) {
+            actor_ids.push(reference.id);
+        }
+        references.push(reference);
+    }
+    let mut genre_ids = Vec::new();
+    for genre in &record.genres {
+        let reference = resolve(db, RefKind::Genre, genre).await?;
+        if !genre_ids.contains(&reference.id) {
+            genre_ids.push(reference.id);
+        }
+        references.push(reference);
+    }
+
+    for actor_id in actor_ids {
+        let rel = movie_actor::ActiveModel {
+            movie_id: Set(created.id),
+            actor_id: Set(actor_id),
+        };
+        audit::insert(db, rel).await.map_err(db_error)?;
+    }
+    for genre_id in genre_ids {
+        let rel = movie_genre::ActiveModel {
+            movie_id: Set(created.id),
+            genre_id: Set(genre_id),
+        };
+        audit::insert(db, rel).await.map_err(db_error)?;
+    }
+    revisions::record(db, &created, RevisionAction::Create, None)
+        .await
+        .map_err(db_error)?;
+
+    Ok(RecordReport {
+        record: position,
+        status: ImportStatus::Created,
+        movie_id: Some(created.id),
+        title: Some(created.title),
+        references,
+        error: None,
+    })
+}
+
+/// Busca la fila a la que apunta `reference` o la crea.
+async fn resolve<C: ConnectionTrait>(
+    db: &C,
+    kind: RefKind,
+    reference: &EntityRef,
+) -> Result<ReferenceReport, String> {
+    let (name, external_id) = reference.parts();
+    if name.is_none() && external_id.is_none() {
+        return Err(format!(
+            "{} reference needs a name or an external_id",
+            kind_name(kind)
+        ));
+    }
+
+    let found = match kind {
+        RefKind::Director => {
+            find_or_create::<director::Entity, director::ActiveModel>(
+                db,
+                [
+                    director::Column::Id,
+                    director::Column::Name,
+                    director::Column::ExternalId,
+                    director::Column::DeletedAt,
+                ],
+                name,
+                external_id,
+            )
+            .await
+        }
+        RefKind::Actor => {
+            find_or_create::<actor::Entity, actor::ActiveModel>(
+                db,
+                [
+                    actor::Column::Id,
+                    actor::Column::Name,
+                    actor::Column::ExternalId,
+                    actor::Column::DeletedAt,
+                ],
+                name,
+                external_id,
+            )
+            .await
+        }
+        RefKind::Genre => {
+            find_or_create::<genre::Entity, genre::ActiveModel>(
+                db,
+                [
+                    genre::Column::Id,
+                    genre::Column::Name,
+                    genre::Column::ExternalId,
+                    genre::Column::DeletedAt,
+                ],
+                name,
+                external_id,
+            )
+            .await
+        }
+    };
+
+    match found.map_err(db_error)? {
+        Resolution::Found(id, name, status) => Ok(ReferenceReport {
+            kind,
+            id,
+            name,
+            status,
+        }),
+        Resolution::Unknown => Err(format!(
+            "unknown {} external_id '{}' and no name to create it",
+            kind_name(kind),
+            external_id.unwrap_or_default()
+        )),
+        Resolution::Trashed => Err(trashed_error(
+            kind_name(kind),
+            external_id.unwrap_or_default(),
+        )),
+    }
+}
+
+fn trashed_error(kind: &str, external_id: &str) -> String {
+    format!(
+        "{} with external_id '{}' is in the trash; restore or purge it first",
+        kind, external_id
+    )
+}
+
+fn kind_name(kind: RefKind) -> &'static str {
+    match kind {
+        RefKind::Director => "director",
+        RefKind::Actor => "actor",
+        RefKind::Genre => "genre",
+    }
+}
+
+/// Resultado de `find_or_create`.
+enum Resolution {
+    Found(i32, String, ImportStatus),
+    /// Solo hay `external_id`, no existe y sin nombre no se puede crear.
+    Unknown,
+    /// El `external_id` es de una fila de la papelera.
+    Trashed,
+}
+
+/// Directores, actores y géneros comparten forma (`id`, `name`, `external_id`,
+/// `deleted_at`), así que se resuelven con la misma consulta. El `external_id`
+/// manda sobre el nombre: dos personas pueden llamarse igual. Las filas de la
+/// papelera no se reutilizan.
+async fn find_or_create<E, A>(
+    db: &impl ConnectionTrait,
+    [id_col, name_col, external_col, deleted_col]: [E::Column; 4],
+    name: Option<&str>,
+    external_id: Option<&str>,
+) -> Result<Resolution, DbErr>
+where
+    E: EntityTrait,
+    E::Model: IntoActiveModel<A> + Audited,
+    A: ActiveModelTrait<Entity = E> + ActiveModelBehavior + Send,
+{
+    let existing = match (external_id, name) {
+        (Some(external_id), _) => {
+            E::find()
+                .filter(external_col.eq(external_id))
+                .one(db)
+                .await?
+        }
+        (None, Some(name)) => {
+            E::find()
+                .filter(name_col.eq(name))
+                .filter(deleted_col.is_null())
+                .order_by_asc(id_col)
+                .one(db)
+                .await?
+        }
+        (None, None) => None,
+    };
+
+    if let Some(model) = &existing {
+        // Solo puede pasar buscando por external_id, que es único también en la papelera.
+        let deleted_at: Option<DateTimeUtc> = ModelTrait::get(model, deleted_col).unwrap();
+        if deleted_at.is_some() {
+            return Ok(Resolution::Trashed);
+        }
+    }
+
+    let (model, status) = match (existing, name) {
+        (Some(model), _) => (model, ImportStatus::Matched),
+        (None, Some(name)) => {
+            let mut active = A::default();
+            active.set(name_col, name.into());
+            active.set(external_col, external_id.map(str::to_string).into());
+            (audit::insert(db, active).await?, ImportStatus::Created)
+        }
+        (None, None) => return Ok(Resolution::Unknown),
+    };
+
+    Ok(Resolution::Found(
+        ModelTrait::get(&model, id_col).unwrap(),
+        ModelTrait::get(&model, name_col).unwrap(),
+        status,
+    ))
+}
+
+/// Lee un array JSON o NDJSON (una película por línea). Los registros que no se
+/// pueden interpretar se devuelven como `Err` para que consten en el informe.
+pub fn parse_body(body: &[u8], ndjson: bool) -> Result<Vec<ParsedRecord>, String> {
+    let parse = |value: serde_json::Value| {
+        serde_json::from_value::<ImportMovie>(value).map_err(|e| format!("Invalid record: {}", e))
+    };
+
+    if ndjson {
+        let text = std::str::from_utf8(body).map_err(|e| format!("Invalid UTF-8: {}", e))?;
+        Ok(text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                let record = serde_json::from_str(line)
+                    .map_err(|e| format!("Invalid JSON: {}", e))
+                    .and_then(parse);
+                (i + 1, record)
+            })
+            .collect())
+    } else {
+        let values: Vec<serde_json::Value> =
+            serde_json::from_slice(body).map_err(|e| format!("Expected a JSON array: {}", e))?;
+        Ok(values
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| (i + 1, parse(value)))
+            .collect())
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ImportQuery {
+    dry_run: Option<bool>,
+}
+
+// POST /import
+#[utoipa::path(
+    post,
+    path = "/import",
+    request_body(
+        content = Vec<ImportMovie>,
+        content_type = "application/json",
+        description = "Array JSON de películas, NDJSON (una por línea) con Content-Type application/x-ndjson, o documentos schema.org/Movie (un objeto, un array o @graph) con Content-Type application/ld+json"
+    ),
+    responses(
+        (status = 200, description = "Informe por registro", body = ImportReport),
+        (status = 400, description = "Bad Request"),
+        (status = 403, description = "Requiere rol editor", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    params(
+        ("dry_run" = Option<bool>, Query, description = "Valida y resuelve sin guardar nada")
+    )
+)]
+pub async fn import_movies(
+    _auth: Authorized<require::Editor>,
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    query: web::Query<ImportQuery>,
+    body: web::Bytes,
+) -> impl Responder {
+    let content_type = req
+        .headers()
+        .get(actix_web::http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/json");
+    let ndjson = [
+        "application/x-ndjson",
+        "application/ndjson",
+        "application/jsonl",
+    ]
+    .iter()
+    .any(|ct| content_type.starts_with(ct));
+
+    let parsed = if jsonld::is_jsonld(content_type) {
+        jsonld::parse_body(&body)
+    } else {
+        parse_body(&body, ndjson)
+    };
+    let records = match parsed {
+        Ok(records) => records,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+
+    match run(db.get_ref(), records, query.dry_run.unwrap_or(false)).await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
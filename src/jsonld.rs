@@ -0,0 +1,235 @@
+// Documentos schema.org (JSON-LD) para películas y personas.
+//
+// Las fichas (`GET /movies/{id}`, `/directors/{id}`, `/actors/{id}`) devuelven
+// `schema.org/Movie` o `schema.org/Person` si se piden con
+// `Accept: application/ld+json`, y `POST /import` acepta ese mismo formato con
+// `Content-Type: application/ld+json`. El `external_id` viaja como `identifier`.
+
+use crate::import::{EntityRef, ImportMovie, ParsedRecord};
+use crate::models::{actor, director};
+use crate::route_handler::MovieFull;
+use actix_web::HttpRequest;
+use actix_web::http::header;
+use serde::{Deserialize, Serialize};
+
+pub const JSONLD_CONTENT_TYPE: &str = "application/ld+json";
+
+const SCHEMA_CONTEXT: &str = "https://schema.org";
+
+/// `true` si la cabecera `Accept` pide JSON-LD.
+pub fn wants_jsonld(req: &HttpRequest) -> bool {
+    req.headers()
+        .get_all(header::ACCEPT)
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|media| media.split(';').next().unwrap_or("").trim() == JSONLD_CONTENT_TYPE)
+}
+
+/// `true` si el cuerpo de la petición es JSON-LD.
+pub fn is_jsonld(content_type: &str) -> bool {
+    content_type.starts_with(JSONLD_CONTENT_TYPE)
+}
+
+/// Un valor o una lista: JSON-LD permite ambas formas para cualquier propiedad.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T> OneOrMany<T> {
+    fn into_vec(self) -> Vec<T> {
+        match self {
+            OneOrMany::One(value) => vec![value],
+            OneOrMany::Many(values) => values,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Person {
+    #[serde(rename = "@context", skip_serializing_if = "Option::is_none")]
+    context: Option<&'static str>,
+    #[serde(rename = "@type")]
+    kind: &'static str,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    identifier: Option<String>,
+}
+
+impl Person {
+    fn new(name: String, identifier: Option<String>) -> Self {
+        Person {
+            context: None,
+            kind: "Person",
+            name,
+            identifier,
+        }
+    }
+
+    /// Documento completo, con `@context`.
+    fn document(name: String, identifier: Option<String>) -> Self {
+        Person {
+            context: Some(SCHEMA_CONTEXT),
+            ..Person::new(name, identifier)
+        }
+    }
+}
+
+impl From<director::Model> for Person {
+    fn from(director: director::Model) -> Self {
+        Person::document(director.name, director.external_id)
+    }
+}
+
+impl From<actor::Model> for Person {
+    fn from(actor: actor::Model) -> Self {
+        Person::document(actor.name, actor.external_id)
+    }
+}
+
+#[derive(Serialize)]
+pub struct Movie {
+    #[serde(rename = "@context")]
+    context: &'static str,
+    #[serde(rename = "@type")]
+    kind: &'static str,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    identifier: Option<String>,
+    #[serde(rename = "datePublished", skip_serializing_if = "Option::is_none")]
+    date_published: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    director: Option<Person>,
+    actor: Vec<Person>,
+    genre: Vec<String>,
+}
+
+impl Movie {
+    pub fn new(movie: MovieFull, external_id: Option<String>) -> Self {
+        Movie {
+            context: SCHEMA_CONTEXT,
+            kind: "Movie",
+            name: movie.title,
+            identifier: external_id,
+            date_published: movie.year.map(|y| y.to_string()),
+            director: movie.director.map(|d| Person::new(d.name, d.external_id)),
+            actor: movie
+                .actors
+                .into_iter()
+                .map(|a| Person::new(a.name, a.external_id))
+                .collect(),
+            genre: movie.genres.into_iter().map(|g| g.name).collect(),
+        }
+    }
+}
+
+/// Persona de entrada: un objeto `Person` o solo el nombre.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PersonInput {
+    Name(String),
+    Person {
+        name: Option<String>,
+        identifier: Option<String>,
+    },
+}
+
+impl From<PersonInput> for EntityRef {
+    fn from(person: PersonInput) -> Self {
+        match person {
+            PersonInput::Name(name) => EntityRef::Name(name),
+            PersonInput::Person { name, identifier } => EntityRef::Full {
+                name,
+                external_id: identifier,
+            },
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct MovieInput {
+    #[serde(rename = "@type")]
+    kind: Option<String>,
+    name: String,
+    identifier: Option<String>,
+    #[serde(rename = "datePublished")]
+    date_published: Option<String>,
+    director: Option<OneOrMany<PersonInput>>,
+    actor: Option<OneOrMany<PersonInput>>,
+    genre: Option<OneOrMany<String>>,
+}
+
+impl MovieInput {
+    fn into_import(self) -> Result<ImportMovie, String> {
+        if let Some(kind) = self.kind.as_deref().filter(|k| *k != "Movie") {
+            return Err(format!("Expected @type Movie, found {}", kind));
+        }
+        // `datePublished` puede ser un año o una fecha ISO 8601 (`1979-05-25`).
+        let year = match self.date_published.as_deref() {
+            None => None,
+            Some(date) => Some(
+                date.get(..4)
+                    .and_then(|y| y.parse().ok())
+                    .ok_or_else(|| format!("Invalid datePublished: {}", date))?,
+            ),
+        };
+        // `movies.director_id` es obligatorio y admite un solo director.
+        let director = self
+            .director
+            .map(OneOrMany::into_vec)
+            .unwrap_or_default()
+            .into_iter()
+            .next()
+            .ok_or_else(|| "Missing director".to_string())?;
+
+        Ok(ImportMovie {
+            title: self.name,
+            year,
+            external_id: self.identifier,
+            director: director.into(),
+            actors: self
+                .actor
+                .map(OneOrMany::into_vec)
+                .unwrap_or_default()
+                .into_iter()
+                .map(EntityRef::from)
+                .collect(),
+            genres: self
+                .genre
+                .map(OneOrMany::into_vec)
+                .unwrap_or_default()
+                .into_iter()
+                .map(EntityRef::Name)
+                .collect(),
+        })
+    }
+}
+
+/// Lee un `Movie`, un array de `Movie` o un objeto con `@graph`, y los convierte
+/// en registros de importación.
+pub fn parse_body(body: &[u8]) -> Result<Vec<ParsedRecord>, String> {
+    let document: serde_json::Value =
+        serde_json::from_slice(body).map_err(|e| format!("Invalid JSON-LD: {}", e))?;
+    let nodes = match document {
+        serde_json::Value::Array(nodes) => nodes,
+        serde_json::Value::Object(mut object) => match object.remove("@graph") {
+            Some(serde_json::Value::Array(nodes)) => nodes,
+            Some(_) => return Err("@graph must be an array".to_string()),
+            None => vec![serde_json::Value::Object(object)],
+        },
+        _ => return Err("Expected a JSON-LD object or array".to_string()),
+    };
+
+    Ok(nodes
+        .into_iter()
+        .enumerate()
+        .map(|(i, node)| {
+            let record = serde_json::from_value::<MovieInput>(node)
+                .map_err(|e| format!("Invalid record: {}", e))
+                .and_then(MovieInput::into_import);
+            (i + 1, record)
+        })
+        .collect())
+}
@@ -1,10 +1,36 @@
+mod audit;
+mod auth;
+mod autocomplete;
+mod cli;
+mod config;
+mod cors;
+mod csv_io;
 mod db;
+mod etag;
+mod export;
+mod facets;
+mod health;
+mod imdb;
+mod import;
+mod jsonld;
+mod metrics;
 mod models;
+mod negotiate;
+mod problem;
+mod rate_limit;
+mod revisions;
 mod route_handler;
+mod search;
+mod shutdown;
+mod telemetry;
+mod trash;
+mod users;
 
-use actix_cors::Cors;
+use actix_web::middleware::from_fn;
 use actix_web::{App, HttpServer, web};
+use auth::SecurityAddon;
 use db::establish_connection;
+use negotiate::AlternativeFormats;
 use route_handler::*;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
@@ -14,13 +40,53 @@ use utoipa_swagger_ui::SwaggerUi;
     paths(
         route_handler::list_movies_full,
         route_handler::list_movies,
+        route_handler::get_movie,
+        route_handler::update_movie,
+        route_handler::delete_movie,
         route_handler::add_movie,
         route_handler::list_directors,
+        route_handler::get_director,
+        route_handler::update_director,
+        route_handler::delete_director,
         route_handler::add_director,
         route_handler::list_actors,
+        route_handler::get_actor,
+        route_handler::update_actor,
+        route_handler::delete_actor,
         route_handler::add_actor,
         route_handler::list_genres,
-        route_handler::add_genre
+        route_handler::add_genre,
+        route_handler::update_genre,
+        route_handler::delete_genre,
+        autocomplete::autocomplete,
+        import::import_movies,
+        csv_io::export_movies_csv,
+        csv_io::import_movies_csv,
+        csv_io::export_directors_csv,
+        csv_io::import_directors_csv,
+        csv_io::export_actors_csv,
+        csv_io::import_actors_csv,
+        csv_io::export_genres_csv,
+        csv_io::import_genres_csv,
+        export::export_catalog,
+        revisions::list_revisions,
+        revisions::diff_revision,
+        revisions::revert_revision,
+        trash::list_trash,
+        trash::restore_movie,
+        trash::restore_director,
+        trash::restore_actor,
+        trash::restore_genre,
+        metrics::metrics,
+        health::healthz,
+        health::readyz,
+        users::register,
+        users::login,
+        users::refresh,
+        users::logout,
+        users::me,
+        users::set_role,
+        audit::list_audit
     ),
     components(
         schemas(
@@ -31,45 +97,209 @@ use utoipa_swagger_ui::SwaggerUi;
             route_handler::CreateMovie,
             route_handler::CreateActor,
             route_handler::CreateGenre,
+            facets::Facets,
+            facets::FacetBucket,
+            autocomplete::Suggestion,
+            autocomplete::SuggestionKind,
+            import::EntityRef,
+            import::ImportMovie,
+            import::ImportStatus,
+            import::RefKind,
+            import::ReferenceReport,
+            import::RecordReport,
+            import::ImportSummary,
+            import::ImportReport,
+            csv_io::CsvRowError,
+            csv_io::CsvImportReport,
+            users::Credentials,
+            users::RefreshRequest,
+            users::TokenResponse,
+            users::SetRole,
+            models::Role,
+            problem::ProblemDetails,
+            health::HealthStatus,
+            health::Liveness,
+            health::ComponentStatus,
+            health::ReadinessComponents,
+            health::Readiness,
+            models::RevisionAction,
+            revisions::RevisionRef,
+            revisions::MovieSnapshot,
+            revisions::Revision,
+            revisions::FieldChange,
+            revisions::RevisionDiff,
+            models::AuditAction,
+            audit::AuditEntry,
+            audit::AuditPage,
+            trash::TrashItem,
+            trash::Trash,
         )
     ),
     tags(
         (name = "movies", description = "Gestión de películas"),
         (name = "directors", description = "Gestión de directores"),
         (name = "actors", description = "Gestión de actores"),
-        (name = "genres", description = "Gestión de géneros")
-    )
+        (name = "genres", description = "Gestión de géneros"),
+        (name = "auth", description = "Usuarios, sesiones y roles"),
+        (name = "revisions", description = "Historial de cambios de las películas"),
+        (name = "trash", description = "Papelera: elementos borrados y restauración"),
+        (name = "audit", description = "Registro de auditoría de los cambios del catálogo"),
+        (name = "metrics", description = "Métricas para Prometheus"),
+        (name = "health", description = "Sondas de vida y disponibilidad")
+    ),
+    modifiers(&AlternativeFormats, &SecurityAddon)
 )]
 pub struct ApiDoc;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    let db = establish_connection().await;
-    HttpServer::new(move || {
-        App::new()
-            .wrap(
-                Cors::default()
-                    .allow_any_origin()
-                    .allow_any_method()
-                    .allow_any_header()
-                    .max_age(3600),
-            )
-            .app_data(web::Data::new(db.clone()))
-            .route("/movies", web::get().to(list_movies))
-            .route("/movies", web::post().to(add_movie))
-            .route("/movies/full", web::get().to(list_movies_full))
-            .route("/directors", web::get().to(list_directors))
-            .route("/directors", web::post().to(add_director))
-            .route("/actors", web::get().to(list_actors))
-            .route("/actors", web::post().to(add_actor))
-            .route("/genres", web::get().to(list_genres))
-            .route("/genres", web::post().to(add_genre))
-            .service(
-                SwaggerUi::new("/swagger-ui/{_:.*}")
-                    .url("/api-doc/openapi.json", ApiDoc::openapi()),
-            )
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        dotenv::dotenv().ok();
+        telemetry::init_cli(config::AppConfig::from_env().log.format);
+        if let Err(e) = cli::run(&args).await {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    dotenv::dotenv().ok();
+    let config = config::AppConfig::from_env();
+    let tracer_provider = telemetry::init(&config.log, &config.otel);
+    cors::warn_if_permissive(&config.cors);
+    let metrics = web::Data::new(metrics::Metrics::default());
+    let mut db = establish_connection().await;
+    {
+        let metrics = metrics.clone();
+        db.set_metric_callback(move |info| metrics.observe_query(info));
+    }
+    let jwt_keys = users::JwtKeys::from_env();
+    // Compartido entre todos los workers para que los límites sean globales.
+    let rate_limiter = web::Data::new(rate_limit::RateLimiter::new(config.rate_limit.clone()));
+    let lifecycle = web::Data::new(shutdown::Lifecycle::default());
+    // Para cerrar el pool cuando los workers hayan terminado.
+    let pool = db.clone();
+    let server = HttpServer::new({
+        let lifecycle = lifecycle.clone();
+        move || {
+            App::new()
+                // Después de autenticar, para contar las peticiones por autor y no
+                // por la credencial que se envíe, que puede inventarse.
+                .wrap(from_fn(rate_limit::limit))
+                // Registrado antes que CORS para que este lo envuelva y también
+                // las respuestas 401 lleven las cabeceras CORS.
+                .wrap(from_fn(auth::authenticate))
+                .wrap(cors::build(&config.cors))
+                // Fuera del límite de peticiones y de la autenticación, para contar
+                // también los 429 y los 401.
+                .wrap(from_fn(metrics::track))
+                // Dentro del span de la petición, para conocer su identificador.
+                .wrap(from_fn(shutdown::track_in_flight))
+                // El más externo: el span cubre también CORS y el límite de peticiones.
+                .wrap(from_fn(telemetry::trace_requests))
+                .app_data(web::Data::new(db.clone()))
+                .app_data(web::Data::new(jwt_keys.clone()))
+                .app_data(rate_limiter.clone())
+                .app_data(metrics.clone())
+                .app_data(lifecycle.clone())
+                .route("/movies", web::get().to(list_movies))
+                .route("/movies", web::post().to(add_movie))
+                .route("/movies/full", web::get().to(list_movies_full))
+                .route(
+                    "/movies/export.csv",
+                    web::get().to(csv_io::export_movies_csv),
+                )
+                .route("/movies/import", web::post().to(csv_io::import_movies_csv))
+                .route("/movies/{id}", web::get().to(get_movie))
+                .route("/movies/{id}", web::put().to(update_movie))
+                .route("/movies/{id}", web::delete().to(delete_movie))
+                .route("/movies/{id}/restore", web::post().to(trash::restore_movie))
+                .route(
+                    "/movies/{id}/revisions",
+                    web::get().to(revisions::list_revisions),
+                )
+                .route(
+                    "/movies/{id}/revisions/{rev}/diff",
+                    web::get().to(revisions::diff_revision),
+                )
+                .route(
+                    "/movies/{id}/revisions/{rev}/revert",
+                    web::post().to(revisions::revert_revision),
+                )
+                .route("/directors", web::get().to(list_directors))
+                .route("/directors", web::post().to(add_director))
+                .route(
+                    "/directors/export.csv",
+                    web::get().to(csv_io::export_directors_csv),
+                )
+                .route(
+                    "/directors/import",
+                    web::post().to(csv_io::import_directors_csv),
+                )
+                .route("/directors/{id}", web::get().to(get_director))
+                .route("/directors/{id}", web::put().to(update_director))
+                .route("/directors/{id}", web::delete().to(delete_director))
+                .route(
+                    "/directors/{id}/restore",
+                    web::post().to(trash::restore_director),
+                )
+                .route("/actors", web::get().to(list_actors))
+                .route("/actors", web::post().to(add_actor))
+                .route(
+                    "/actors/export.csv",
+                    web::get().to(csv_io::export_actors_csv),
+                )
+                .route("/actors/import", web::post().to(csv_io::import_actors_csv))
+                .route("/actors/{id}", web::get().to(get_actor))
+                .route("/actors/{id}", web::put().to(update_actor))
+                .route("/actors/{id}", web::delete().to(delete_actor))
+                .route("/actors/{id}/restore", web::post().to(trash::restore_actor))
+                .route("/genres", web::get().to(list_genres))
+                .route("/genres", web::post().to(add_genre))
+                .route(
+                    "/genres/export.csv",
+                    web::get().to(csv_io::export_genres_csv),
+                )
+                .route("/genres/import", web::post().to(csv_io::import_genres_csv))
+                .route("/genres/{id}", web::put().to(update_genre))
+                .route("/genres/{id}", web::delete().to(delete_genre))
+                .route("/genres/{id}/restore", web::post().to(trash::restore_genre))
+                .route("/trash", web::get().to(trash::list_trash))
+                .route("/auth/register", web::post().to(users::register))
+                .route("/auth/login", web::post().to(users::login))
+                .route("/auth/refresh", web::post().to(users::refresh))
+                .route("/auth/logout", web::post().to(users::logout))
+                .route("/auth/me", web::get().to(users::me))
+                .route("/admin/users/{id}/role", web::put().to(users::set_role))
+                .route("/admin/audit", web::get().to(audit::list_audit))
+                .route("/autocomplete", web::get().to(autocomplete::autocomplete))
+                .route("/export", web::get().to(export::export_catalog))
+                .route("/metrics", web::get().to(metrics::metrics))
+                .route("/healthz", web::get().to(health::healthz))
+                .route("/readyz", web::get().to(health::readyz))
+                .service(
+                    web::resource("/import")
+                        .app_data(web::PayloadConfig::new(import::IMPORT_MAX_BYTES))
+                        .route(web::post().to(import::import_movies)),
+                )
+                .service(
+                    SwaggerUi::new("/swagger-ui/{_:.*}")
+                        .url("/api-doc/openapi.json", ApiDoc::openapi()),
+                )
+        }
     })
-    .bind(("127.0.0.1", 8080))?
-    .run()
-    .await
+    .shutdown_signal(shutdown::signal(lifecycle.clone(), config.shutdown.clone()))
+    .shutdown_timeout(config.shutdown.timeout_seconds)
+    .bind(("127.0.0.1", 8080))?;
+    tracing::info!("Listening on http://127.0.0.1:8080");
+    let result = server.run().await;
+    shutdown::finish(&lifecycle, pool).await;
+    if let Some(provider) = tracer_provider {
+        // Envía los spans pendientes antes de salir.
+        if let Err(e) = provider.shutdown() {
+            tracing::warn!("Flushing traces failed: {}", e);
+        }
+    }
+    result
 }
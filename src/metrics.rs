@@ -0,0 +1,330 @@
+// Métricas en formato de texto de Prometheus (`GET /metrics`).
+//
+// Las peticiones HTTP se etiquetan con la plantilla de la ruta registrada en
+// `main.rs` (`/movies/{id}`), no con la ruta real, para que el número de series no
+// crezca con cada id. Las consultas se cuentan con el callback de métricas de
+// SeaORM, etiquetadas con la tabla y la operación. El estado del pool y el tamaño
+// del catálogo se leen en cada scrape.
+
+use crate::models::{actor, director, genre, movie};
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpResponse, Responder, web};
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, PaginatorTrait, QueryFilter};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const METRICS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Límites superiores (en segundos) de los buckets de los histogramas.
+const BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Etiqueta de las peticiones que no coinciden con ninguna ruta.
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+#[derive(Default)]
+struct Histogram {
+    /// Observaciones por bucket (no acumuladas; se acumulan al exportar).
+    counts: [u64; BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: Duration) {
+        let seconds = value.as_secs_f64();
+        if let Some(index) = BUCKETS.iter().position(|bound| seconds <= *bound) {
+            self.counts[index] += 1;
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (bound, count) in BUCKETS.iter().zip(self.counts) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{},le=\"{}\"}} {}",
+                name, labels, bound, cumulative
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_bucket{{{},le=\"+Inf\"}} {}",
+            name, labels, self.count
+        );
+        let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, self.sum);
+        let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, self.count);
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    /// (método, ruta, estado) -> peticiones.
+    requests: BTreeMap<(String, String, u16), u64>,
+    /// (método, ruta) -> latencia.
+    latency: BTreeMap<(String, String), Histogram>,
+    /// (tabla, operación) -> duración de las consultas.
+    queries: BTreeMap<(String, String), Histogram>,
+    /// (tabla, operación) -> consultas fallidas.
+    query_errors: BTreeMap<(String, String), u64>,
+}
+
+#[derive(Default)]
+pub struct Metrics {
+    registry: Mutex<Registry>,
+}
+
+impl Metrics {
+    fn observe_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        let mut registry = self.registry.lock().unwrap();
+        *registry
+            .requests
+            .entry((method.to_string(), route.to_string(), status))
+            .or_default() += 1;
+        registry
+            .latency
+            .entry((method.to_string(), route.to_string()))
+            .or_default()
+            .observe(elapsed);
+    }
+
+    /// Callback para `DatabaseConnection::set_metric_callback`.
+    pub fn observe_query(&self, info: &sea_orm::metric::Info<'_>) {
+        let key = classify(&info.statement.sql);
+        let mut registry = self.registry.lock().unwrap();
+        if info.failed {
+            *registry.query_errors.entry(key.clone()).or_default() += 1;
+        }
+        registry
+            .queries
+            .entry(key)
+            .or_default()
+            .observe(info.elapsed);
+    }
+
+    fn render(&self, out: &mut String) {
+        let registry = self.registry.lock().unwrap();
+
+        header(
+            out,
+            "http_requests_total",
+            "counter",
+            "HTTP requests by route and status.",
+        );
+        for ((method, route, status), count) in &registry.requests {
+            let _ = writeln!(
+                out,
+                "http_requests_total{{method=\"{}\",route=\"{}\",status=\"{}\"}} {}",
+                method,
+                escape(route),
+                status,
+                count
+            );
+        }
+
+        header(
+            out,
+            "http_request_duration_seconds",
+            "histogram",
+            "HTTP request latency by route.",
+        );
+        for ((method, route), histogram) in &registry.latency {
+            let labels = format!("method=\"{}\",route=\"{}\"", method, escape(route));
+            histogram.render(out, "http_request_duration_seconds", &labels);
+        }
+
+        header(
+            out,
+            "db_query_duration_seconds",
+            "histogram",
+            "Database query duration by table and operation.",
+        );
+        for ((table, operation), histogram) in &registry.queries {
+            let labels = format!("table=\"{}\",operation=\"{}\"", escape(table), operation);
+            histogram.render(out, "db_query_duration_seconds", &labels);
+        }
+
+        header(
+            out,
+            "db_query_errors_total",
+            "counter",
+            "Failed database queries by table and operation.",
+        );
+        for ((table, operation), count) in &registry.query_errors {
+            let _ = writeln!(
+                out,
+                "db_query_errors_total{{table=\"{}\",operation=\"{}\"}} {}",
+                escape(table),
+                operation,
+                count
+            );
+        }
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Tabla principal y operación de una sentencia SQL generada por SeaORM:
+/// `SELECT ... FROM "movies"` -> `("movies", "select")`.
+fn classify(sql: &str) -> (String, String) {
+    let operation = sql
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let keyword = match operation.as_str() {
+        "insert" => " INTO ",
+        "update" => "UPDATE ",
+        _ => " FROM ",
+    };
+    // Se salta las subconsultas (`SELECT COUNT(*) FROM (SELECT ... FROM "movies")`).
+    let table = sql
+        .match_indices(keyword)
+        .filter_map(|(index, _)| sql[index + keyword.len()..].split_whitespace().next())
+        .find(|name| !name.starts_with('('))
+        .map(|name| {
+            name.trim_start_matches('"')
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+                .collect()
+        })
+        .filter(|name: &String| !name.is_empty())
+        .unwrap_or_else(|| "other".to_string());
+    (table, operation)
+}
+
+/// Middleware: cuenta cada petición y su latencia por ruta y estado.
+pub async fn track(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let metrics = req.app_data::<web::Data<Metrics>>().cloned();
+    let method = req.method().to_string();
+    let route = req
+        .match_pattern()
+        .unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+
+    let started = Instant::now();
+    let result = next.call(req).await;
+    if let Some(metrics) = metrics {
+        let status = match &result {
+            Ok(response) => response.status(),
+            Err(e) => e.as_response_error().status_code(),
+        };
+        metrics.observe_request(&method, &route, status.as_u16(), started.elapsed());
+    }
+    result
+}
+
+async fn render_gauges(db: &DatabaseConnection, out: &mut String) -> Result<(), DbErr> {
+    let pool = db.get_sqlite_connection_pool();
+    let size = pool.size() as usize;
+    let idle = pool.num_idle();
+    header(
+        out,
+        "db_pool_connections",
+        "gauge",
+        "Open connections in the pool by state.",
+    );
+    let _ = writeln!(out, "db_pool_connections{{state=\"idle\"}} {}", idle);
+    let _ = writeln!(
+        out,
+        "db_pool_connections{{state=\"in_use\"}} {}",
+        size.saturating_sub(idle)
+    );
+    header(
+        out,
+        "db_pool_max_connections",
+        "gauge",
+        "Maximum connections in the pool.",
+    );
+    let _ = writeln!(
+        out,
+        "db_pool_max_connections {}",
+        pool.options().get_max_connections()
+    );
+
+    // Sin contar la papelera.
+    let counts = [
+        (
+            "movies",
+            movie::Entity::find()
+                .filter(movie::Column::DeletedAt.is_null())
+                .count(db)
+                .await?,
+        ),
+        (
+            "directors",
+            director::Entity::find()
+                .filter(director::Column::DeletedAt.is_null())
+                .count(db)
+                .await?,
+        ),
+        (
+            "actors",
+            actor::Entity::find()
+                .filter(actor::Column::DeletedAt.is_null())
+                .count(db)
+                .await?,
+        ),
+        (
+            "genres",
+            genre::Entity::find()
+                .filter(genre::Column::DeletedAt.is_null())
+                .count(db)
+                .await?,
+        ),
+    ];
+    header(
+        out,
+        "catalog_entities",
+        "gauge",
+        "Catalog size by entity, excluding the trash.",
+    );
+    for (entity, count) in counts {
+        let _ = writeln!(out, "catalog_entities{{entity=\"{}\"}} {}", entity, count);
+    }
+    Ok(())
+}
+
+/// Métricas en formato de texto de Prometheus
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    responses(
+        (status = 200, description = "Métricas de peticiones, base de datos y catálogo", content_type = "text/plain", body = String),
+        (status = 500, description = "Error al leer los contadores del catálogo")
+    ),
+    tag = "metrics"
+)]
+pub async fn metrics(
+    db: web::Data<DatabaseConnection>,
+    metrics: web::Data<Metrics>,
+) -> impl Responder {
+    let mut out = String::new();
+    if let Err(e) = render_gauges(db.get_ref(), &mut out).await {
+        return HttpResponse::InternalServerError().body(format!("Error: {}", e));
+    }
+    metrics.render(&mut out);
+    HttpResponse::Ok()
+        .content_type(METRICS_CONTENT_TYPE)
+        .body(out)
+}
@@ -2,6 +2,129 @@
 
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Rol de un usuario o una clave de API. El orden importa: cada rol incluye los
+/// permisos de los anteriores.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(None)")]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    #[sea_orm(string_value = "viewer")]
+    Viewer,
+    #[sea_orm(string_value = "contributor")]
+    Contributor,
+    #[sea_orm(string_value = "editor")]
+    Editor,
+    #[sea_orm(string_value = "admin")]
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Contributor => "contributor",
+            Role::Editor => "editor",
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        [Role::Viewer, Role::Contributor, Role::Editor, Role::Admin]
+            .into_iter()
+            .find(|role| role.as_str() == s)
+    }
+}
+
+/// Origen de una revisión de película.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize, ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(None)")]
+#[serde(rename_all = "lowercase")]
+pub enum RevisionAction {
+    /// Alta de la película.
+    #[sea_orm(string_value = "create")]
+    Create,
+    #[sea_orm(string_value = "update")]
+    Update,
+    /// Vuelta al estado de una revisión anterior (`reverted_from`).
+    #[sea_orm(string_value = "revert")]
+    Revert,
+    /// Estado de una película anterior al historial, guardado antes de su primer
+    /// cambio.
+    #[sea_orm(string_value = "baseline")]
+    Baseline,
+}
+
+/// Operación anotada en el registro de auditoría.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize, ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(None)")]
+#[serde(rename_all = "lowercase")]
+pub enum AuditAction {
+    #[sea_orm(string_value = "create")]
+    Create,
+    #[sea_orm(string_value = "update")]
+    Update,
+    /// Envío a la papelera.
+    #[sea_orm(string_value = "delete")]
+    Delete,
+    #[sea_orm(string_value = "restore")]
+    Restore,
+    /// Borrado definitivo (`trash purge`, o una relación quitada).
+    #[sea_orm(string_value = "purge")]
+    Purge,
+}
+
+/// Rellena `created_at`/`created_by` al insertar y `updated_at`/`updated_by` en cada
+/// guardado, e incrementa `version`. El autor es el de la petición en curso
+/// (`auth::current_actor`); fuera de una petición (p. ej. desde la CLI) puede no
+/// haberlo.
+fn track_changes<A: ActiveModelTrait>(
+    active: &mut A,
+    insert: bool,
+    columns: [<A::Entity as EntityTrait>::Column; 5],
+) {
+    let [created_at, updated_at, created_by, updated_by, version] = columns;
+    let now = chrono::Utc::now();
+    let actor = crate::auth::current_actor();
+    if insert {
+        active.set(created_at, Some(now).into());
+        active.set(created_by, actor.clone().into());
+    }
+    active.set(updated_at, Some(now).into());
+    active.set(updated_by, actor.into());
+    let next = match active.get(version).into_value() {
+        Some(Value::Int(Some(current))) if !insert => current + 1,
+        _ => 1,
+    };
+    active.set(version, next.into());
+}
+
+/// Entidad con columna `version` (control de concurrencia optimista): cada
+/// guardado la incrementa y `audit::update` solo escribe si no ha cambiado desde
+/// que se leyó la fila.
+pub trait Versioned: EntityTrait {
+    const VERSION: Self::Column;
+
+    fn version(model: &Self::Model) -> i32;
+}
 
 pub mod director {
     use super::*;
@@ -11,6 +134,15 @@ pub mod director {
         #[sea_orm(primary_key)]
         pub id: i32,
         pub name: String,
+        pub external_id: Option<String>,
+        /// Fecha del borrado lógico; solo aparece en la papelera.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub deleted_at: Option<DateTimeUtc>,
+        pub created_at: Option<DateTimeUtc>,
+        pub updated_at: Option<DateTimeUtc>,
+        pub created_by: Option<String>,
+        pub updated_by: Option<String>,
+        pub version: i32,
     }
     #[derive(Copy, Clone, Debug, EnumIter)]
     pub enum Relation {}
@@ -19,7 +151,34 @@ pub mod director {
             panic!("No Relation")
         }
     }
-    impl ActiveModelBehavior for ActiveModel {}
+    #[async_trait::async_trait]
+    impl ActiveModelBehavior for ActiveModel {
+        async fn before_save<C: ConnectionTrait>(
+            mut self,
+            _db: &C,
+            insert: bool,
+        ) -> Result<Self, DbErr> {
+            super::track_changes(
+                &mut self,
+                insert,
+                [
+                    Column::CreatedAt,
+                    Column::UpdatedAt,
+                    Column::CreatedBy,
+                    Column::UpdatedBy,
+                    Column::Version,
+                ],
+            );
+            Ok(self)
+        }
+    }
+    impl super::Versioned for Entity {
+        const VERSION: Column = Column::Version;
+
+        fn version(model: &Model) -> i32 {
+            model.version
+        }
+    }
 }
 
 pub mod actor {
@@ -30,6 +189,15 @@ pub mod actor {
         #[sea_orm(primary_key)]
         pub id: i32,
         pub name: String,
+        pub external_id: Option<String>,
+        /// Fecha del borrado lógico; solo aparece en la papelera.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub deleted_at: Option<DateTimeUtc>,
+        pub created_at: Option<DateTimeUtc>,
+        pub updated_at: Option<DateTimeUtc>,
+        pub created_by: Option<String>,
+        pub updated_by: Option<String>,
+        pub version: i32,
     }
     #[derive(Copy, Clone, Debug, EnumIter)]
     pub enum Relation {}
@@ -38,7 +206,34 @@ pub mod actor {
             panic!("No Relation")
         }
     }
-    impl ActiveModelBehavior for ActiveModel {}
+    #[async_trait::async_trait]
+    impl ActiveModelBehavior for ActiveModel {
+        async fn before_save<C: ConnectionTrait>(
+            mut self,
+            _db: &C,
+            insert: bool,
+        ) -> Result<Self, DbErr> {
+            super::track_changes(
+                &mut self,
+                insert,
+                [
+                    Column::CreatedAt,
+                    Column::UpdatedAt,
+                    Column::CreatedBy,
+                    Column::UpdatedBy,
+                    Column::Version,
+                ],
+            );
+            Ok(self)
+        }
+    }
+    impl super::Versioned for Entity {
+        const VERSION: Column = Column::Version;
+
+        fn version(model: &Model) -> i32 {
+            model.version
+        }
+    }
 }
 
 pub mod genre {
@@ -49,6 +244,15 @@ pub mod genre {
         #[sea_orm(primary_key)]
         pub id: i32,
         pub name: String,
+        pub external_id: Option<String>,
+        /// Fecha del borrado lógico; solo aparece en la papelera.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub deleted_at: Option<DateTimeUtc>,
+        pub created_at: Option<DateTimeUtc>,
+        pub updated_at: Option<DateTimeUtc>,
+        pub created_by: Option<String>,
+        pub updated_by: Option<String>,
+        pub version: i32,
     }
     #[derive(Copy, Clone, Debug, EnumIter)]
     pub enum Relation {}
@@ -57,7 +261,34 @@ pub mod genre {
             panic!("No Relation")
         }
     }
-    impl ActiveModelBehavior for ActiveModel {}
+    #[async_trait::async_trait]
+    impl ActiveModelBehavior for ActiveModel {
+        async fn before_save<C: ConnectionTrait>(
+            mut self,
+            _db: &C,
+            insert: bool,
+        ) -> Result<Self, DbErr> {
+            super::track_changes(
+                &mut self,
+                insert,
+                [
+                    Column::CreatedAt,
+                    Column::UpdatedAt,
+                    Column::CreatedBy,
+                    Column::UpdatedBy,
+                    Column::Version,
+                ],
+            );
+            Ok(self)
+        }
+    }
+    impl super::Versioned for Entity {
+        const VERSION: Column = Column::Version;
+
+        fn version(model: &Model) -> i32 {
+            model.version
+        }
+    }
 }
 
 pub mod movie {
@@ -69,6 +300,16 @@ pub mod movie {
         pub id: i32,
         pub title: String,
         pub director_id: i32,
+        pub year: Option<i32>,
+        pub external_id: Option<String>,
+        /// Fecha del borrado lógico; solo aparece en la papelera.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pub deleted_at: Option<DateTimeUtc>,
+        pub created_at: Option<DateTimeUtc>,
+        pub updated_at: Option<DateTimeUtc>,
+        pub created_by: Option<String>,
+        pub updated_by: Option<String>,
+        pub version: i32,
     }
     #[derive(Copy, Clone, Debug, EnumIter)]
     pub enum Relation {}
@@ -77,7 +318,34 @@ pub mod movie {
             panic!("No Relation")
         }
     }
-    impl ActiveModelBehavior for ActiveModel {}
+    #[async_trait::async_trait]
+    impl ActiveModelBehavior for ActiveModel {
+        async fn before_save<C: ConnectionTrait>(
+            mut self,
+            _db: &C,
+            insert: bool,
+        ) -> Result<Self, DbErr> {
+            super::track_changes(
+                &mut self,
+                insert,
+                [
+                    Column::CreatedAt,
+                    Column::UpdatedAt,
+                    Column::CreatedBy,
+                    Column::UpdatedBy,
+                    Column::Version,
+                ],
+            );
+            Ok(self)
+        }
+    }
+    impl super::Versioned for Entity {
+        const VERSION: Column = Column::Version;
+
+        fn version(model: &Model) -> i32 {
+            model.version
+        }
+    }
 }
 
 pub mod movie_actor {
@@ -119,3 +387,126 @@ pub mod movie_genre {
     }
     impl ActiveModelBehavior for ActiveModel {}
 }
+
+pub mod api_key {
+    use super::*;
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "api_keys")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: i32,
+        pub name: String,
+        pub prefix: String,
+        pub key_hash: String,
+        pub created_at: DateTimeUtc,
+        pub revoked_at: Option<DateTimeUtc>,
+        pub role: super::Role,
+    }
+    #[derive(Copy, Clone, Debug, EnumIter)]
+    pub enum Relation {}
+    impl sea_orm::RelationTrait for Relation {
+        fn def(&self) -> sea_orm::RelationDef {
+            panic!("No Relation")
+        }
+    }
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod user {
+    use super::*;
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+    #[sea_orm(table_name = "users")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: i32,
+        pub email: String,
+        #[serde(skip_serializing)]
+        pub password_hash: String,
+        pub created_at: DateTimeUtc,
+        pub role: super::Role,
+    }
+    #[derive(Copy, Clone, Debug, EnumIter)]
+    pub enum Relation {}
+    impl sea_orm::RelationTrait for Relation {
+        fn def(&self) -> sea_orm::RelationDef {
+            panic!("No Relation")
+        }
+    }
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod refresh_token {
+    use super::*;
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "refresh_tokens")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: i32,
+        pub user_id: i32,
+        pub token_hash: String,
+        pub created_at: DateTimeUtc,
+        pub expires_at: DateTimeUtc,
+        pub revoked_at: Option<DateTimeUtc>,
+    }
+    #[derive(Copy, Clone, Debug, EnumIter)]
+    pub enum Relation {}
+    impl sea_orm::RelationTrait for Relation {
+        fn def(&self) -> sea_orm::RelationDef {
+            panic!("No Relation")
+        }
+    }
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod movie_revision {
+    use super::*;
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "movie_revisions")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: i32,
+        pub movie_id: i32,
+        /// Número de revisión dentro de la película, desde 1.
+        pub revision: i32,
+        pub action: super::RevisionAction,
+        pub reverted_from: Option<i32>,
+        /// `revisions::MovieSnapshot` serializado.
+        pub snapshot: Json,
+        pub created_at: DateTimeUtc,
+        pub created_by: Option<String>,
+    }
+    #[derive(Copy, Clone, Debug, EnumIter)]
+    pub enum Relation {}
+    impl sea_orm::RelationTrait for Relation {
+        fn def(&self) -> sea_orm::RelationDef {
+            panic!("No Relation")
+        }
+    }
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+pub mod audit_log {
+    use super::*;
+    #[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+    #[sea_orm(table_name = "audit_log")]
+    pub struct Model {
+        #[sea_orm(primary_key)]
+        pub id: i32,
+        pub created_at: DateTimeUtc,
+        pub actor: Option<String>,
+        pub request_id: Option<String>,
+        pub action: super::AuditAction,
+        pub entity: String,
+        pub entity_id: String,
+        pub before: Option<Json>,
+        pub after: Option<Json>,
+    }
+    #[derive(Copy, Clone, Debug, EnumIter)]
+    pub enum Relation {}
+    impl sea_orm::RelationTrait for Relation {
+        fn def(&self) -> sea_orm::RelationDef {
+            panic!("No Relation")
+        }
+    }
+    impl ActiveModelBehavior for ActiveModel {}
+}
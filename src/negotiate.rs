@@ -0,0 +1,428 @@
+// Negociación de contenido para los listados y las fichas: la respuesta se
+// serializa en JSON, CSV, YAML o XML según la cabecera `Accept` o el parámetro
+// `?format=`, que tiene prioridad. Si no se puede servir ninguno, `406`.
+//
+// CSV y XML se generan a partir del `serde_json::Value` de la respuesta:
+// en CSV los objetos anidados se aplanan (`director.name`) y las listas se unen
+// con `|`, como en `/movies/export.csv`.
+
+use actix_web::http::header;
+use actix_web::{HttpRequest, HttpResponse, web};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::Modify;
+use utoipa::openapi::path::{ParameterBuilder, ParameterIn, PathItemType};
+use utoipa::openapi::{ObjectBuilder, OpenApi, RefOr, Required, SchemaType};
+
+/// Rutas cuya respuesta `200` admite los formatos alternativos.
+const NEGOTIATED_PATHS: &[&str] = &[
+    "/movies",
+    "/movies/full",
+    "/movies/{id}",
+    "/directors",
+    "/directors/{id}",
+    "/actors",
+    "/actors/{id}",
+    "/genres",
+    "/autocomplete",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Csv,
+    Yaml,
+    Xml,
+}
+
+impl Format {
+    const ALL: [Format; 4] = [Format::Json, Format::Csv, Format::Yaml, Format::Xml];
+
+    fn name(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Csv => "csv",
+            Format::Yaml => "yaml",
+            Format::Xml => "xml",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Csv => "text/csv; charset=utf-8",
+            Format::Yaml => "application/yaml",
+            Format::Xml => "application/xml",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "json" => Some(Format::Json),
+            "csv" => Some(Format::Csv),
+            "yaml" | "yml" => Some(Format::Yaml),
+            "xml" => Some(Format::Xml),
+            _ => None,
+        }
+    }
+
+    fn from_media_type(media: &str) -> Option<Self> {
+        match media.to_lowercase().as_str() {
+            "*/*" | "application/*" | "application/json" => Some(Format::Json),
+            "text/*" | "text/csv" => Some(Format::Csv),
+            "application/yaml" | "application/x-yaml" | "text/yaml" => Some(Format::Yaml),
+            "application/xml" | "text/xml" => Some(Format::Xml),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct FormatQuery {
+    format: Option<String>,
+}
+
+/// Formato de la respuesta: `?format=` si está presente; si no, el tipo de
+/// `Accept` con mayor `q` que se sepa servir. Sin `Accept`, JSON.
+///
+/// XML solo se elige si es el tipo explícito con mayor `q`: los navegadores
+/// envían `text/html,…,application/xml;q=0.9,*/*;q=0.8` y deben recibir JSON.
+pub fn negotiate(req: &HttpRequest) -> Result<Format, String> {
+    let requested = web::Query::<FormatQuery>::from_query(req.query_string())
+        .ok()
+        .and_then(|q| q.into_inner().format);
+    if let Some(name) = requested {
+        return Format::from_name(&name).ok_or_else(|| format!("Unsupported format: {}", name));
+    }
+
+    let mut ranges: Vec<(&str, f32)> = req
+        .headers()
+        .get_all(header::ACCEPT)
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(|range| {
+            let mut parts = range.split(';').map(str::trim);
+            let media = parts.next().filter(|m| !m.is_empty())?;
+            let q = parts
+                .filter_map(|p| p.strip_prefix("q="))
+                .find_map(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((media, q))
+        })
+        .collect();
+    if ranges.is_empty() {
+        return Ok(Format::Json);
+    }
+    // Orden estable: a igual `q`, manda el orden de la cabecera.
+    ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let accepted = || ranges.iter().filter(|(_, q)| *q > 0.0);
+    let best = accepted()
+        .find_map(|(media, _)| Format::from_media_type(media))
+        .ok_or_else(|| {
+            format!(
+                "Not acceptable. Supported: {}",
+                Format::ALL.map(Format::content_type).join(", ")
+            )
+        })?;
+    if best == Format::Xml {
+        let xml_first = accepted()
+            .find(|(media, _)| !media.ends_with("/*"))
+            .is_some_and(|(media, _)| Format::from_media_type(media) == Some(Format::Xml));
+        let json_accepted =
+            accepted().any(|(media, _)| Format::from_media_type(media) == Some(Format::Json));
+        if !xml_first && json_accepted {
+            return Ok(Format::Json);
+        }
+    }
+    Ok(best)
+}
+
+/// `200` con `value` en el formato negociado, o `406`.
+pub fn respond<T: Serialize>(req: &HttpRequest, value: &T) -> HttpResponse {
+    let format = match negotiate(req) {
+        Ok(format) => format,
+        Err(e) => return HttpResponse::NotAcceptable().body(e),
+    };
+    let body = match format {
+        Format::Json => return HttpResponse::Ok().json(value),
+        Format::Yaml => serde_yaml::to_string(value).map_err(|e| e.to_string()),
+        Format::Csv => serde_json::to_value(value)
+            .map_err(|e| e.to_string())
+            .and_then(|v| to_csv(&v)),
+        Format::Xml => serde_json::to_value(value)
+            .map(|v| to_xml(&v))
+            .map_err(|e| e.to_string()),
+    };
+    match body {
+        Ok(body) => HttpResponse::Ok()
+            .content_type(format.content_type())
+            .body(body),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
+
+//
+// --- CSV ---
+//
+
+/// Una fila por elemento si la respuesta es una lista (o un listado paginado con
+/// `results`); si no, una sola fila.
+fn to_csv(value: &Value) -> Result<String, String> {
+    let items: Vec<&Value> = match value {
+        Value::Array(items) => items.iter().collect(),
+        Value::Object(object) => match object.get("results") {
+            Some(Value::Array(results)) => results.iter().collect(),
+            _ => vec![value],
+        },
+        _ => vec![value],
+    };
+
+    let rows: Vec<Vec<(String, String)>> = items
+        .into_iter()
+        .map(|item| {
+            let mut row = Vec::new();
+            flatten("", item, &mut row);
+            row
+        })
+        .collect();
+
+    // Columnas en el orden en que aparecen por primera vez.
+    let mut headers: Vec<&str> = Vec::new();
+    for (key, _) in rows.iter().flatten() {
+        if !headers.contains(&key.as_str()) {
+            headers.push(key);
+        }
+    }
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(&headers).map_err(|e| e.to_string())?;
+    for row in &rows {
+        let record = headers.iter().map(|header| {
+            row.iter()
+                .find(|(key, _)| key == header)
+                .map(|(_, value)| value.as_str())
+                .unwrap_or("")
+        });
+        writer.write_record(record).map_err(|e| e.to_string())?;
+    }
+    let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
+
+/// Aplana `value` en pares `(columna, valor)`. En las listas de objetos cada
+/// campo se une con `|`: `actors.name` = `Sigourney Weaver|Tom Skerritt`.
+fn flatten(prefix: &str, value: &Value, row: &mut Vec<(String, String)>) {
+    let key = |name: &str| {
+        if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", prefix, name)
+        }
+    };
+    match value {
+        Value::Object(object) => {
+            for (name, value) in object {
+                flatten(&key(name), value, row);
+            }
+        }
+        Value::Array(items) => {
+            let mut columns: Vec<(String, Vec<String>)> = Vec::new();
+            for item in items {
+                let mut item_row = Vec::new();
+                flatten(prefix, item, &mut item_row);
+                for (column, value) in item_row {
+                    match columns.iter_mut().find(|(c, _)| *c == column) {
+                        Some((_, values)) => values.push(value),
+                        None => columns.push((column, vec![value])),
+                    }
+                }
+            }
+            row.extend(
+                columns
+                    .into_iter()
+                    .map(|(column, values)| (column, values.join("|"))),
+            );
+        }
+        scalar => row.push((prefix.to_string(), scalar_text(scalar))),
+    }
+}
+
+fn scalar_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+//
+// --- XML ---
+//
+
+/// `<response>` con un elemento por campo; las listas, como elementos `<item>`.
+fn to_xml(value: &Value) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+    write_element(&mut out, "response", value);
+    out
+}
+
+fn write_element(out: &mut String, name: &str, value: &Value) {
+    match value {
+        Value::Null => {
+            out.push_str(&format!("<{}/>", name));
+        }
+        Value::Object(object) => {
+            out.push_str(&format!("<{}>", name));
+            for (field, value) in object {
+                write_element(out, field, value);
+            }
+            out.push_str(&format!("</{}>", name));
+        }
+        Value::Array(items) => {
+            out.push_str(&format!("<{}>", name));
+            for item in items {
+                write_element(out, "item", item);
+            }
+            out.push_str(&format!("</{}>", name));
+        }
+        scalar => {
+            out.push_str(&format!(
+                "<{}>{}</{}>",
+                name,
+                escape_xml(&scalar_text(scalar)),
+                name
+            ));
+        }
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+//
+// --- OpenAPI ---
+//
+
+/// Documenta en `ApiDoc` los tipos alternativos de la respuesta `200` y el
+/// parámetro `format` de las rutas negociadas.
+pub struct AlternativeFormats;
+
+impl Modify for AlternativeFormats {
+    fn modify(&self, openapi: &mut OpenApi) {
+        for (path, item) in openapi.paths.paths.iter_mut() {
+            if !NEGOTIATED_PATHS.contains(&path.as_str()) {
+                continue;
+            }
+            let Some(operation) = item.operations.get_mut(&PathItemType::Get) else {
+                continue;
+            };
+
+            if let Some(RefOr::T(response)) = operation.responses.responses.get_mut("200")
+                && let Some(json) = response.content.get("application/json").cloned()
+            {
+                for format in [Format::Csv, Format::Yaml, Format::Xml] {
+                    let media = format.content_type().split(';').next().unwrap_or_default();
+                    response.content.insert(media.to_string(), json.clone());
+                }
+            }
+
+            let parameter = ParameterBuilder::new()
+                .name("format")
+                .parameter_in(ParameterIn::Query)
+                .required(Required::False)
+                .description(Some(
+                    "Formato de la respuesta (tiene prioridad sobre Accept): json, csv, yaml o xml",
+                ))
+                .schema(Some(
+                    ObjectBuilder::new()
+                        .schema_type(SchemaType::String)
+                        .enum_values(Some(Format::ALL.map(Format::name))),
+                ))
+                .build();
+            operation
+                .parameters
+                .get_or_insert_with(Vec::new)
+                .push(parameter);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    fn negotiate_accept(accept: &str) -> Result<Format, String> {
+        negotiate(
+            &TestRequest::default()
+                .insert_header((header::ACCEPT, accept))
+                .to_http_request(),
+        )
+    }
+
+    #[test]
+    fn defaults_to_json() {
+        assert_eq!(
+            negotiate(&TestRequest::default().to_http_request()),
+            Ok(Format::Json)
+        );
+        assert_eq!(negotiate_accept("*/*"), Ok(Format::Json));
+        assert_eq!(negotiate_accept("application/*"), Ok(Format::Json));
+    }
+
+    #[test]
+    fn picks_the_highest_q() {
+        assert_eq!(negotiate_accept("text/csv"), Ok(Format::Csv));
+        assert_eq!(
+            negotiate_accept("application/json;q=0.5, application/yaml"),
+            Ok(Format::Yaml)
+        );
+        assert_eq!(
+            negotiate_accept("text/csv;q=0.2, application/xml;q=0.9, application/json;q=0.4"),
+            Ok(Format::Xml)
+        );
+        // A igual `q`, el orden de la cabecera.
+        assert_eq!(
+            negotiate_accept("application/yaml, text/csv"),
+            Ok(Format::Yaml)
+        );
+        assert_eq!(
+            negotiate_accept("application/xml;q=0, application/json;q=0.1"),
+            Ok(Format::Json)
+        );
+        assert_eq!(negotiate_accept("application/xml"), Ok(Format::Xml));
+    }
+
+    #[test]
+    fn browsers_get_json() {
+        let browser = "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8";
+        assert_eq!(negotiate_accept(browser), Ok(Format::Json));
+        // XML explícito por delante de JSON sí se respeta.
+        assert_eq!(
+            negotiate_accept("application/xml, application/json;q=0.5"),
+            Ok(Format::Xml)
+        );
+    }
+
+    #[test]
+    fn format_parameter_wins() {
+        let req = TestRequest::with_uri("/movies?format=yml")
+            .insert_header((header::ACCEPT, "application/xml"))
+            .to_http_request();
+        assert_eq!(negotiate(&req), Ok(Format::Yaml));
+        let req = TestRequest::with_uri("/movies?format=pdf").to_http_request();
+        assert!(negotiate(&req).is_err());
+    }
+
+    #[test]
+    fn rejects_unsupported_types() {
+        assert!(negotiate_accept("text/html").is_err());
+        assert!(negotiate_accept("application/json;q=0").is_err());
+    }
+}
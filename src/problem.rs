@@ -0,0 +1,34 @@
+// Respuestas de error en formato `application/problem+json` (RFC 7807).
+
+use actix_web::HttpResponse;
+use actix_web::http::StatusCode;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+pub const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
+
+#[derive(Serialize, ToSchema)]
+pub struct ProblemDetails {
+    /// Siempre `about:blank`: el significado lo da `status`.
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub title: String,
+    pub status: u16,
+    pub detail: String,
+    /// Identificador de la petición (también en `X-Request-Id`), para buscarla en
+    /// los logs. Lo añade el middleware de `telemetry.rs`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+pub fn problem(status: StatusCode, detail: impl Into<String>) -> HttpResponse {
+    HttpResponse::build(status)
+        .content_type(PROBLEM_CONTENT_TYPE)
+        .json(ProblemDetails {
+            kind: "about:blank",
+            title: status.canonical_reason().unwrap_or("Error").to_string(),
+            status: status.as_u16(),
+            detail: detail.into(),
+            request_id: None,
+        })
+}
@@ -0,0 +1,188 @@
+// Limitación de peticiones por cliente con token buckets en memoria.
+//
+// El cliente es el autor autenticado (`api-key:<id>` o `user:<id>`) o, si no se
+// ha autenticado, su IP: una credencial no válida no abre un bucket nuevo. Cada
+// cliente tiene un bucket para lecturas y otro para escrituras; las búsquedas
+// consumen `search_cost` fichas.
+// Todas las respuestas llevan las cabeceras `RateLimit-*`; al agotarse el bucket
+// se responde `429` con `Retry-After`.
+
+use crate::auth::Principal;
+use crate::config::RateLimitConfig;
+use crate::problem::problem;
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use actix_web::http::{Method, StatusCode};
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpMessage, web};
+use hashlink::LruCache;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Rutas que cuestan `search_cost` fichas en lugar de una.
+const SEARCH_PATHS: &[&str] = &["/movies/full", "/export"];
+
+/// Sondas y métricas, sin límite: un `429` haría que el orquestador diera por
+/// caída una instancia sana.
+const EXEMPT_PATHS: &[&str] = &["/healthz", "/readyz", "/metrics"];
+
+/// Buckets en memoria; al llegar al máximo se descarta el usado hace más tiempo.
+const MAX_BUCKETS: usize = 10_000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Class {
+    Read,
+    Write,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Resultado de consumir fichas de un bucket.
+struct Decision {
+    allowed: bool,
+    limit: u32,
+    remaining: u32,
+    /// Segundos hasta que el bucket vuelve a estar lleno.
+    reset: u64,
+    /// Segundos hasta que habrá fichas suficientes (solo si `!allowed`).
+    retry_after: u64,
+}
+
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<LruCache<(String, Class), Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        RateLimiter {
+            config,
+            buckets: Mutex::new(LruCache::new(MAX_BUCKETS)),
+        }
+    }
+
+    fn limits(&self, class: Class) -> (f64, f64) {
+        match class {
+            Class::Read => (self.config.read_burst as f64, self.config.read_per_second),
+            Class::Write => (self.config.write_burst as f64, self.config.write_per_second),
+        }
+    }
+
+    fn take(&self, client: String, class: Class, cost: u32) -> Decision {
+        let (burst, rate) = self.limits(class);
+        // Una petición que cuesta más que el bucket entero no pasaría nunca.
+        let cost = (cost as f64).min(burst);
+        let now = Instant::now();
+
+        let key = (client, class);
+        let mut buckets = self.buckets.lock().unwrap();
+        // `contains_key` también lo marca como el usado más recientemente.
+        if !buckets.contains_key(&key) {
+            buckets.insert(
+                key.clone(),
+                Bucket {
+                    tokens: burst,
+                    updated: now,
+                },
+            );
+        }
+        let bucket = buckets.get_mut(&key).expect("bucket just inserted");
+        bucket.tokens =
+            (bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * rate).min(burst);
+        bucket.updated = now;
+
+        let allowed = bucket.tokens >= cost;
+        if allowed {
+            bucket.tokens -= cost;
+        }
+        let seconds = |tokens: f64| (tokens.max(0.0) / rate).ceil() as u64;
+        Decision {
+            allowed,
+            limit: burst as u32,
+            remaining: bucket.tokens.floor() as u32,
+            reset: seconds(burst - bucket.tokens),
+            retry_after: if allowed {
+                0
+            } else {
+                seconds(cost - bucket.tokens)
+            },
+        }
+    }
+}
+
+/// Autor autenticado por `auth::authenticate` o, si no hay, la IP del cliente.
+fn client_key(req: &ServiceRequest) -> String {
+    if let Some(principal) = req.extensions().get::<Principal>() {
+        return principal.actor.clone();
+    }
+    format!(
+        "ip:{}",
+        req.peer_addr()
+            .map(|addr| addr.ip().to_string())
+            .unwrap_or_default()
+    )
+}
+
+fn set_headers(headers: &mut HeaderMap, decision: &Decision) {
+    let values = [
+        ("ratelimit-limit", decision.limit as u64),
+        ("ratelimit-remaining", decision.remaining as u64),
+        ("ratelimit-reset", decision.reset),
+    ];
+    for (name, value) in values {
+        headers.insert(HeaderName::from_static(name), HeaderValue::from(value));
+    }
+}
+
+/// Middleware: consume fichas del bucket del cliente y rechaza con `429` cuando
+/// no quedan.
+pub async fn limit<B: MessageBody>(
+    req: ServiceRequest,
+    next: Next<B>,
+) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    let Some(limiter) = req
+        .app_data::<web::Data<RateLimiter>>()
+        .filter(|limiter| limiter.config.enabled)
+        .filter(|_| !EXEMPT_PATHS.contains(&req.path()))
+        .cloned()
+    else {
+        return next
+            .call(req)
+            .await
+            .map(ServiceResponse::map_into_left_body);
+    };
+
+    let class = match *req.method() {
+        Method::GET | Method::HEAD | Method::OPTIONS => Class::Read,
+        _ => Class::Write,
+    };
+    let cost = if SEARCH_PATHS.contains(&req.path()) {
+        limiter.config.search_cost
+    } else {
+        1
+    };
+    let decision = limiter.take(client_key(&req), class, cost);
+
+    if !decision.allowed {
+        let mut response = problem(
+            StatusCode::TOO_MANY_REQUESTS,
+            format!(
+                "Rate limit exceeded; retry in {} seconds",
+                decision.retry_after
+            ),
+        );
+        set_headers(response.headers_mut(), &decision);
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, HeaderValue::from(decision.retry_after));
+        return Ok(req.into_response(response).map_into_right_body());
+    }
+
+    let mut response = next.call(req).await?;
+    set_headers(response.headers_mut(), &decision);
+    Ok(response.map_into_left_body())
+}
@@ -0,0 +1,524 @@
+// Historial de revisiones de las películas.
+//
+// Cada alta, modificación o reversión de una película guarda una instantánea
+// completa (título, año, director, reparto y géneros) en `movie_revisions`, en la
+// misma transacción que el cambio. Las películas anteriores al historial guardan
+// su estado como revisión `baseline` justo antes de su primer cambio.
+
+use crate::auth::{Authorized, current_actor, require};
+use crate::etag;
+use crate::models::{
+    RevisionAction, actor, director, genre, movie, movie_actor, movie_genre, movie_revision,
+};
+use crate::route_handler::{CreateMovie, ValidationError, check_movie_if_match, movie_response};
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use chrono::Utc;
+use sea_orm::ActiveValue::Set;
+use sea_orm::prelude::DateTimeUtc;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+/// Director, actor o género tal como se llamaba en la revisión.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RevisionRef {
+    pub id: i32,
+    pub name: String,
+}
+
+/// Estado de una película en una revisión.
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct MovieSnapshot {
+    pub title: String,
+    pub year: Option<i32>,
+    pub director: RevisionRef,
+    /// Ordenados por id.
+    pub actors: Vec<RevisionRef>,
+    /// Ordenados por id.
+    pub genres: Vec<RevisionRef>,
+}
+
+fn ids(refs: &[RevisionRef]) -> Vec<i32> {
+    refs.iter().map(|r| r.id).collect()
+}
+
+impl MovieSnapshot {
+    /// Mismos datos y relaciones, aunque algún nombre haya cambiado desde entonces.
+    fn same_as(&self, other: &MovieSnapshot) -> bool {
+        self.title == other.title
+            && self.year == other.year
+            && self.director.id == other.director.id
+            && ids(&self.actors) == ids(&other.actors)
+            && ids(&self.genres) == ids(&other.genres)
+    }
+
+    fn into_create(self) -> CreateMovie {
+        CreateMovie {
+            actor_ids: ids(&self.actors),
+            genre_ids: ids(&self.genres),
+            title: self.title,
+            director_id: self.director.id,
+            year: self.year,
+        }
+    }
+}
+
+/// Estado actual de `movie` según la base de datos, incluidas las relaciones con
+/// actores o géneros que estén en la papelera.
+async fn snapshot<C: ConnectionTrait>(
+    db: &C,
+    movie: &movie::Model,
+) -> Result<MovieSnapshot, DbErr> {
+    let director = director::Entity::find_by_id(movie.director_id)
+        .one(db)
+        .await?
+        .map(|d| RevisionRef {
+            id: d.id,
+            name: d.name,
+        })
+        .ok_or_else(|| DbErr::RecordNotFound(format!("Director {}", movie.director_id)))?;
+
+    let actor_ids: Vec<i32> = movie_actor::Entity::find()
+        .filter(movie_actor::Column::MovieId.eq(movie.id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|ma| ma.actor_id)
+        .collect();
+    let actors = actor::Entity::find()
+        .filter(actor::Column::Id.is_in(actor_ids))
+        .order_by_asc(actor::Column::Id)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|a| RevisionRef {
+            id: a.id,
+            name: a.name,
+        })
+        .collect();
+
+    let genre_ids: Vec<i32> = movie_genre::Entity::find()
+        .filter(movie_genre::Column::MovieId.eq(movie.id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|mg| mg.genre_id)
+        .collect();
+    let genres = genre::Entity::find()
+        .filter(genre::Column::Id.is_in(genre_ids))
+        .order_by_asc(genre::Column::Id)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|g| RevisionRef {
+            id: g.id,
+            name: g.name,
+        })
+        .collect();
+
+    Ok(MovieSnapshot {
+        title: movie.title.clone(),
+        year: movie.year,
+        director,
+        actors,
+        genres,
+    })
+}
+
+fn parse_snapshot(revision: &movie_revision::Model) -> Result<MovieSnapshot, DbErr> {
+    serde_json::from_value(revision.snapshot.clone()).map_err(|e| DbErr::Json(e.to_string()))
+}
+
+async fn latest<C: ConnectionTrait>(
+    db: &C,
+    movie_id: i32,
+) -> Result<Option<movie_revision::Model>, DbErr> {
+    movie_revision::Entity::find()
+        .filter(movie_revision::Column::MovieId.eq(movie_id))
+        .order_by_desc(movie_revision::Column::Revision)
+        .one(db)
+        .await
+}
+
+fn to_json(snapshot: &MovieSnapshot) -> Result<Value, DbErr> {
+    serde_json::to_value(snapshot).map_err(|e| DbErr::Json(e.to_string()))
+}
+
+/// Guarda el estado actual de `movie` como su siguiente revisión; llamar después
+/// de escribir la película y sus relaciones. Una modificación que no cambia nada
+/// no genera revisión.
+pub async fn record<C: ConnectionTrait>(
+    db: &C,
+    movie: &movie::Model,
+    action: RevisionAction,
+    reverted_from: Option<i32>,
+) -> Result<(), DbErr> {
+    let snapshot = snapshot(db, movie).await?;
+    let last = latest(db, movie.id).await?;
+    if let (RevisionAction::Update, Some(last)) = (action, &last)
+        && parse_snapshot(last)?.same_as(&snapshot)
+    {
+        return Ok(());
+    }
+    movie_revision::ActiveModel {
+        movie_id: Set(movie.id),
+        revision: Set(last.map_or(1, |last| last.revision + 1)),
+        action: Set(action),
+        reverted_from: Set(reverted_from),
+        snapshot: Set(to_json(&snapshot)?),
+        created_at: Set(Utc::now()),
+        created_by: Set(current_actor()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    Ok(())
+}
+
+/// Si `movie` aún no tiene historial, guarda su estado actual como revisión
+/// `baseline`, con la fecha y el autor de su último cambio; llamar antes de
+/// modificarla.
+pub async fn ensure_baseline<C: ConnectionTrait>(
+    db: &C,
+    movie: &movie::Model,
+) -> Result<(), DbErr> {
+    let existing = movie_revision::Entity::find()
+        .filter(movie_revision::Column::MovieId.eq(movie.id))
+        .count(db)
+        .await?;
+    if existing > 0 {
+        return Ok(());
+    }
+    let snapshot = snapshot(db, movie).await?;
+    movie_revision::ActiveModel {
+        movie_id: Set(movie.id),
+        revision: Set(1),
+        action: Set(RevisionAction::Baseline),
+        reverted_from: Set(None),
+        snapshot: Set(to_json(&snapshot)?),
+        created_at: Set(movie
+            .updated_at
+            .or(movie.created_at)
+            .unwrap_or_else(Utc::now)),
+        created_by: Set(movie.updated_by.clone()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+    Ok(())
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Revision {
+    pub revision: i32,
+    pub action: RevisionAction,
+    /// Revisión recuperada, en las de tipo `revert`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reverted_from: Option<i32>,
+    pub created_at: DateTimeUtc,
+    /// `user:<id>`, `api-key:<id>` o `cli:<comando>`; vacío si se desconoce.
+    pub created_by: Option<String>,
+    pub snapshot: MovieSnapshot,
+}
+
+impl TryFrom<movie_revision::Model> for Revision {
+    type Error = DbErr;
+
+    fn try_from(model: movie_revision::Model) -> Result<Self, DbErr> {
+        Ok(Revision {
+            snapshot: parse_snapshot(&model)?,
+            revision: model.revision,
+            action: model.action,
+            reverted_from: model.reverted_from,
+            created_at: model.created_at,
+            created_by: model.created_by,
+        })
+    }
+}
+
+/// Película fuera de la papelera, o `None`.
+async fn live_movie(db: &DatabaseConnection, id: i32) -> Result<Option<movie::Model>, DbErr> {
+    movie::Entity::find_by_id(id)
+        .filter(movie::Column::DeletedAt.is_null())
+        .one(db)
+        .await
+}
+
+async fn find_revision(
+    db: &DatabaseConnection,
+    movie_id: i32,
+    revision: i32,
+) -> Result<Option<movie_revision::Model>, DbErr> {
+    movie_revision::Entity::find()
+        .filter(movie_revision::Column::MovieId.eq(movie_id))
+        .filter(movie_revision::Column::Revision.eq(revision))
+        .one(db)
+        .await
+}
+
+// GET /movies/{id}/revisions
+#[utoipa::path(
+    get,
+    path = "/movies/{id}/revisions",
+    responses(
+        (status = 200, description = "Revisiones de la película, la más reciente primero", body = Vec<Revision>),
+        (status = 404, description = "Movie not found"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    params(
+        ("id" = i32, Path, description = "Id de la película")
+    ),
+    tag = "revisions"
+)]
+pub async fn list_revisions(
+    db: web::Data<DatabaseConnection>,
+    id: web::Path<i32>,
+) -> impl Responder {
+    let id = id.into_inner();
+    match live_movie(db.get_ref(), id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return HttpResponse::NotFound().body("Movie not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+    let revisions: Result<Vec<Revision>, DbErr> = async {
+        movie_revision::Entity::find()
+            .filter(movie_revision::Column::MovieId.eq(id))
+            .order_by_desc(movie_revision::Column::Revision)
+            .all(db.get_ref())
+            .await?
+            .into_iter()
+            .map(Revision::try_from)
+            .collect()
+    }
+    .await;
+    match revisions {
+        Ok(revisions) => HttpResponse::Ok().json(revisions),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
+
+/// Cambio de un campo entre dos revisiones. En `actors` y `genres` se indican
+/// además los añadidos y los quitados.
+#[derive(Serialize, ToSchema)]
+pub struct FieldChange {
+    /// `title`, `year`, `director`, `actors` o `genres`.
+    pub field: &'static str,
+    #[schema(value_type = Object)]
+    pub before: Value,
+    #[schema(value_type = Object)]
+    pub after: Value,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub added: Vec<RevisionRef>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<RevisionRef>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RevisionDiff {
+    pub revision: i32,
+    /// Revisión con la que se compara; vacía si es la primera.
+    pub against: Option<i32>,
+    pub changes: Vec<FieldChange>,
+}
+
+#[derive(Deserialize)]
+pub struct DiffQuery {
+    against: Option<i32>,
+}
+
+/// Campos que cambian de `before` a `after`. Director, actores y géneros se
+/// comparan por id: un cambio de nombre no es un cambio de la película.
+fn diff(before: Option<&MovieSnapshot>, after: &MovieSnapshot) -> Vec<FieldChange> {
+    fn json<T: Serialize>(value: &T) -> Value {
+        serde_json::to_value(value).unwrap_or(Value::Null)
+    }
+    fn scalar(field: &'static str, before: Value, after: Value) -> FieldChange {
+        FieldChange {
+            field,
+            before,
+            after,
+            added: Vec::new(),
+            removed: Vec::new(),
+        }
+    }
+    fn list(field: &'static str, before: &[RevisionRef], after: &[RevisionRef]) -> FieldChange {
+        let (before_ids, after_ids) = (ids(before), ids(after));
+        FieldChange {
+            field,
+            before: json(&before),
+            after: json(&after),
+            added: after
+                .iter()
+                .filter(|r| !before_ids.contains(&r.id))
+                .cloned()
+                .collect(),
+            removed: before
+                .iter()
+                .filter(|r| !after_ids.contains(&r.id))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    let mut changes = Vec::new();
+    if before.map(|b| &b.title) != Some(&after.title) {
+        changes.push(scalar(
+            "title",
+            json(&before.map(|b| &b.title)),
+            json(&after.title),
+        ));
+    }
+    if before.map(|b| b.year) != Some(after.year) {
+        changes.push(scalar(
+            "year",
+            json(&before.and_then(|b| b.year)),
+            json(&after.year),
+        ));
+    }
+    if before.map(|b| b.director.id) != Some(after.director.id) {
+        changes.push(scalar(
+            "director",
+            json(&before.map(|b| &b.director)),
+            json(&after.director),
+        ));
+    }
+    let (actors, genres) = match before {
+        Some(b) => (b.actors.as_slice(), b.genres.as_slice()),
+        None => (&[][..], &[][..]),
+    };
+    if ids(actors) != ids(&after.actors) {
+        changes.push(list("actors", actors, &after.actors));
+    }
+    if ids(genres) != ids(&after.genres) {
+        changes.push(list("genres", genres, &after.genres));
+    }
+    changes
+}
+
+// GET /movies/{id}/revisions/{rev}/diff
+#[utoipa::path(
+    get,
+    path = "/movies/{id}/revisions/{rev}/diff",
+    responses(
+        (status = 200, description = "Campos que cambian respecto a la revisión anterior (o a `against`)", body = RevisionDiff),
+        (status = 404, description = "Película o revisión no encontrada"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    params(
+        ("id" = i32, Path, description = "Id de la película"),
+        ("rev" = i32, Path, description = "Número de revisión"),
+        ("against" = Option<i32>, Query, description = "Revisión con la que comparar; por defecto, la anterior")
+    ),
+    tag = "revisions"
+)]
+pub async fn diff_revision(
+    db: web::Data<DatabaseConnection>,
+    path: web::Path<(i32, i32)>,
+    query: web::Query<DiffQuery>,
+) -> impl Responder {
+    let (id, rev) = path.into_inner();
+    match live_movie(db.get_ref(), id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return HttpResponse::NotFound().body("Movie not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+    let against = query.against.or(Some(rev - 1)).filter(|&n| n > 0);
+
+    let result: Result<Option<RevisionDiff>, DbErr> = async {
+        let Some(after) = find_revision(db.get_ref(), id, rev).await? else {
+            return Ok(None);
+        };
+        let before = match against {
+            Some(n) => match find_revision(db.get_ref(), id, n).await? {
+                Some(before) => Some(parse_snapshot(&before)?),
+                None => return Ok(None),
+            },
+            None => None,
+        };
+        Ok(Some(RevisionDiff {
+            revision: rev,
+            against,
+            changes: diff(before.as_ref(), &parse_snapshot(&after)?),
+        }))
+    }
+    .await;
+    match result {
+        Ok(Some(diff)) => HttpResponse::Ok().json(diff),
+        Ok(None) => HttpResponse::NotFound().body("Revision not found"),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
+
+// POST /movies/{id}/revisions/{rev}/revert
+#[utoipa::path(
+    post,
+    path = "/movies/{id}/revisions/{rev}/revert",
+    responses(
+        (status = 200, description = "Película devuelta al estado de la revisión; se guarda como una revisión nueva", body = movie::Model,
+            headers(("ETag" = String, description = "ETag de la ficha de la película"))),
+        (status = 403, description = "Requiere rol editor", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Película o revisión no encontrada"),
+        (status = 409, description = "El director, algún actor o algún género de la revisión ya no existe o está en la papelera"),
+        (status = 412, description = "La película ha cambiado desde el ETag de `If-Match`", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    params(
+        ("id" = i32, Path, description = "Id de la película"),
+        ("rev" = i32, Path, description = "Número de revisión"),
+        ("If-Match" = Option<String>, Header, description = "ETag de la ficha de la película; si no coincide, `412`")
+    ),
+    tag = "revisions"
+)]
+pub async fn revert_revision(
+    _auth: Authorized<require::Editor>,
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    path: web::Path<(i32, i32)>,
+) -> impl Responder {
+    let (id, rev) = path.into_inner();
+    let existing = match live_movie(db.get_ref(), id).await {
+        Ok(Some(movie)) => movie,
+        Ok(None) => return HttpResponse::NotFound().body("Movie not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    };
+    if let Err(response) = check_movie_if_match(db.get_ref(), &req, &existing).await {
+        return response;
+    }
+    let form = match find_revision(db.get_ref(), id, rev).await {
+        Ok(Some(revision)) => match parse_snapshot(&revision) {
+            Ok(snapshot) => snapshot.into_create(),
+            Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+        },
+        Ok(None) => return HttpResponse::NotFound().body("Revision not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    };
+
+    match form.validate(db.get_ref()).await {
+        Ok(()) => {}
+        Err(ValidationError::Invalid(msg)) => {
+            return HttpResponse::Conflict()
+                .body(format!("Cannot revert to revision {}: {}", rev, msg));
+        }
+        Err(ValidationError::Db(e)) => {
+            return HttpResponse::InternalServerError().body(format!("DB error: {}", e));
+        }
+    }
+
+    let result = db
+        .transaction::<_, movie::Model, DbErr>(|txn| {
+            Box::pin(async move {
+                form.apply(txn, existing, RevisionAction::Revert, Some(rev))
+                    .await
+            })
+        })
+        .await;
+    match result {
+        Ok(movie) => movie_response(db.get_ref(), movie).await,
+        Err(e) if etag::is_stale(&e) => etag::changed_concurrently(),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
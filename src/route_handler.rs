@@ -1,12 +1,67 @@
-use crate::models::{actor, director, genre, movie, movie_actor, movie_genre};
-use actix_web::{HttpResponse, Responder, web};
+use crate::audit;
+use crate::auth::{Authorized, require};
+use crate::etag;
+use crate::facets::{self, Facets};
+use crate::jsonld::{self, JSONLD_CONTENT_TYPE};
+use crate::models::{
+    AuditAction, RevisionAction, actor, director, genre, movie, movie_actor, movie_genre,
+};
+use crate::negotiate;
+use crate::revisions;
+use crate::search;
+use crate::trash::{soft_delete, soft_delete_in};
+use actix_web::http::header;
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 use sea_orm::ActiveValue::Set;
+use sea_orm::prelude::DateTimeUtc;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
-    QueryOrder, QuerySelect,
+    ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, ModelTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, TransactionTrait,
 };
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
+
+/// Filtros `created_since`/`updated_since` de los listados.
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ChangedSince {
+    /// Solo lo creado desde esta fecha (RFC 3339, o `AAAA-MM-DD` a las 00:00 UTC)
+    created_since: Option<String>,
+    /// Solo lo modificado desde esta fecha (RFC 3339, o `AAAA-MM-DD` a las 00:00 UTC)
+    updated_since: Option<String>,
+}
+
+impl ChangedSince {
+    /// Condición sobre las columnas `created_at` y `updated_at` de la entidad. Las
+    /// filas anteriores al seguimiento de cambios no tienen fechas y no la cumplen.
+    fn condition<C: ColumnTrait>(&self, created_at: C, updated_at: C) -> Result<Condition, String> {
+        let mut condition = Condition::all();
+        if let Some(since) = &self.created_since {
+            condition = condition.add(created_at.gte(parse_since("created_since", since)?));
+        }
+        if let Some(since) = &self.updated_since {
+            condition = condition.add(updated_at.gte(parse_since("updated_since", since)?));
+        }
+        Ok(condition)
+    }
+}
+
+pub fn parse_since(name: &str, value: &str) -> Result<DateTimeUtc, String> {
+    let value = value.trim();
+    DateTime::parse_from_rfc3339(value)
+        .map(|date| date.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map(|date| date.and_time(NaiveTime::MIN).and_utc())
+        })
+        .map_err(|_| {
+            format!(
+                "Invalid {} '{}', expected RFC 3339 or YYYY-MM-DD",
+                name, value
+            )
+        })
+}
 
 //
 // --- Director Endpoints ---
@@ -22,10 +77,12 @@ pub struct CreateDirector {
     request_body = CreateDirector,
     responses(
         (status = 201, description = "Director created", body = director::Model),
+        (status = 403, description = "Requiere rol contributor", body = ProblemDetails, content_type = "application/problem+json"),
         (status = 500, description = "Internal Server Error")
     )
 )]
 pub async fn add_director(
+    _auth: Authorized<require::Contributor>,
     db: web::Data<DatabaseConnection>,
     form: web::Json<CreateDirector>,
 ) -> impl Responder {
@@ -34,7 +91,12 @@ pub async fn add_director(
         ..Default::default()
     };
 
-    match director.insert(db.get_ref()).await {
+    let result = db
+        .transaction::<_, director::Model, DbErr>(|txn| {
+            Box::pin(async move { audit::insert(txn, director).await })
+        })
+        .await;
+    match result {
         Ok(model) => HttpResponse::Created().json(model),
         Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
     }
@@ -45,15 +107,206 @@ pub async fn add_director(
     path = "/directors",
     responses(
         (status = 200, description = "List of directors", body = Vec<director::Model>),
+        (status = 400, description = "Fecha mal formada"),
         (status = 500, description = "Internal Server Error")
+    ),
+    params(ChangedSince)
+)]
+pub async fn list_directors(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    since: web::Query<ChangedSince>,
+) -> impl Responder {
+    let since = match since.condition(director::Column::CreatedAt, director::Column::UpdatedAt) {
+        Ok(condition) => condition,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    match director::Entity::find()
+        .filter(director::Column::DeletedAt.is_null())
+        .filter(since)
+        .all(db.get_ref())
+        .await
+    {
+        Ok(directors) => negotiate::respond(&req, &directors),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
+// GET /directors/{id}
+#[utoipa::path(
+    get,
+    path = "/directors/{id}",
+    responses(
+        (status = 200, description = "Director (schema.org/Person con Accept: application/ld+json)", body = director::Model,
+            headers(("ETag" = String, description = "Versión del director y formato de la respuesta"))),
+        (status = 304, description = "Sin cambios desde el ETag de `If-None-Match`"),
+        (status = 404, description = "Director not found"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    params(
+        ("id" = i32, Path, description = "Id del director"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag de una copia anterior; si sigue vigente, `304`")
     )
 )]
-pub async fn list_directors(db: web::Data<DatabaseConnection>) -> impl Responder {
-    match director::Entity::find().all(db.get_ref()).await {
-        Ok(directors) => HttpResponse::Ok().json(directors),
+pub async fn get_director(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    id: web::Path<i32>,
+) -> impl Responder {
+    let model = match director::Entity::find_by_id(id.into_inner())
+        .filter(director::Column::DeletedAt.is_null())
+        .one(db.get_ref())
+        .await
+    {
+        Ok(Some(model)) => model,
+        Ok(None) => return HttpResponse::NotFound().body("Director not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    };
+    let version = etag::version_tag(model.version);
+    let response = if jsonld::wants_jsonld(&req) {
+        HttpResponse::Ok()
+            .content_type(JSONLD_CONTENT_TYPE)
+            .json(jsonld::Person::from(model))
+    } else {
+        negotiate::respond(&req, &model)
+    };
+    etag::conditional_get(&req, &version, response)
+}
+
+// PUT /directors/{id}
+#[utoipa::path(
+    put,
+    path = "/directors/{id}",
+    request_body = CreateDirector,
+    responses(
+        (status = 200, description = "Director updated", body = director::Model,
+            headers(("ETag" = String, description = "Versión del director y formato de la respuesta"))),
+        (status = 403, description = "Requiere rol editor", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Director not found"),
+        (status = 412, description = "El director ha cambiado desde el ETag de `If-Match`", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    params(
+        ("id" = i32, Path, description = "Id del director"),
+        ("If-Match" = Option<String>, Header, description = "ETag del director; si no coincide, `412`")
+    )
+)]
+pub async fn update_director(
+    _auth: Authorized<require::Editor>,
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    id: web::Path<i32>,
+    form: web::Json<CreateDirector>,
+) -> impl Responder {
+    let model = match director::Entity::find_by_id(id.into_inner())
+        .filter(director::Column::DeletedAt.is_null())
+        .one(db.get_ref())
+        .await
+    {
+        Ok(Some(model)) => model,
+        Ok(None) => return HttpResponse::NotFound().body("Director not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    };
+    if let Err(response) = etag::check_if_match(&req, &etag::version_tag(model.version)) {
+        return response;
+    }
+    let mut active: director::ActiveModel = model.clone().into();
+    active.name = Set(form.name.clone());
+    let result = db
+        .transaction::<_, _, DbErr>(|txn| {
+            Box::pin(async move { audit::update(txn, &model, active).await })
+        })
+        .await;
+    match result {
+        Ok(model) => {
+            let version = etag::version_tag(model.version);
+            etag::with_tag(HttpResponse::Ok().json(model), &version)
+        }
+        Err(e) if etag::is_stale(&e) => etag::changed_concurrently(),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
+
+// DELETE /directors/{id}
+#[utoipa::path(
+    delete,
+    path = "/directors/{id}",
+    responses(
+        (status = 204, description = "Director enviado a la papelera"),
+        (status = 403, description = "Requiere rol editor", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Director not found"),
+        (status = 409, description = "El director tiene películas"),
+        (status = 412, description = "El director ha cambiado desde el ETag de `If-Match`", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    params(
+        ("id" = i32, Path, description = "Id del director"),
+        ("If-Match" = Option<String>, Header, description = "ETag del director; si no coincide, `412`")
+    )
+)]
+pub async fn delete_director(
+    _auth: Authorized<require::Editor>,
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    id: web::Path<i32>,
+) -> impl Responder {
+    let id = id.into_inner();
+    let current = match director::Entity::find_by_id(id)
+        .filter(director::Column::DeletedAt.is_null())
+        .one(db.get_ref())
+        .await
+    {
+        Ok(Some(model)) => model,
+        Ok(None) => return HttpResponse::NotFound().body("Director not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    };
+    if let Err(response) = etag::check_if_match(&req, &etag::version_tag(current.version)) {
+        return response;
+    }
+    let txn = match db.begin().await {
+        Ok(txn) => txn,
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    };
+    match soft_delete_in::<_, director::Entity>(
+        &txn,
+        [
+            director::Column::Id,
+            director::Column::DeletedAt,
+            director::Column::Version,
+            director::Column::UpdatedAt,
+            director::Column::UpdatedBy,
+        ],
+        id,
+        current.version,
+    )
+    .await
+    {
+        Ok(true) => {}
+        Ok(false) => return etag::changed_concurrently(),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+    // `movies.director_id` es obligatorio: no se puede dejar una película sin director.
+    // Las películas de la papelera no cuentan, pero no se pueden restaurar mientras
+    // su director siga borrado. Se cuentan en la transacción del borrado y después
+    // de él, con la base de datos ya bloqueada para escribir: ninguna película puede
+    // asignársele entre la comprobación y el borrado. Si hay alguna, se deshace.
+    match movie::Entity::find()
+        .filter(movie::Column::DirectorId.eq(id))
+        .filter(movie::Column::DeletedAt.is_null())
+        .count(&txn)
+        .await
+    {
+        Ok(0) => {}
+        Ok(n) => {
+            return HttpResponse::Conflict().body(format!("Director has {} movies", n));
+        }
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+    match txn.commit().await {
+        Ok(()) => HttpResponse::NoContent().finish(),
         Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
     }
 }
+
 //
 // --- Actor endpoints ---
 //
@@ -69,10 +322,12 @@ pub struct CreateActor {
     request_body = CreateActor,
     responses(
         (status = 201, description = "Actor created", body = actor::Model),
+        (status = 403, description = "Requiere rol contributor", body = ProblemDetails, content_type = "application/problem+json"),
         (status = 500, description = "Internal Server Error")
     )
 )]
 pub async fn add_actor(
+    _auth: Authorized<require::Contributor>,
     db: web::Data<DatabaseConnection>,
     form: web::Json<CreateActor>,
 ) -> impl Responder {
@@ -80,7 +335,12 @@ pub async fn add_actor(
         name: Set(form.name.clone()),
         ..Default::default()
     };
-    match actor.insert(db.get_ref()).await {
+    let result = db
+        .transaction::<_, actor::Model, DbErr>(|txn| {
+            Box::pin(async move { audit::insert(txn, actor).await })
+        })
+        .await;
+    match result {
         Ok(actor) => HttpResponse::Created().json(actor),
         Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
     }
@@ -91,12 +351,177 @@ pub async fn add_actor(
     path = "/actors",
     responses(
         (status = 200, description = "List of actors", body = Vec<actor::Model>),
+        (status = 400, description = "Fecha mal formada"),
         (status = 500, description = "Internal Server Error")
+    ),
+    params(ChangedSince)
+)]
+pub async fn list_actors(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    since: web::Query<ChangedSince>,
+) -> impl Responder {
+    let since = match since.condition(actor::Column::CreatedAt, actor::Column::UpdatedAt) {
+        Ok(condition) => condition,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    match actor::Entity::find()
+        .filter(actor::Column::DeletedAt.is_null())
+        .filter(since)
+        .all(db.get_ref())
+        .await
+    {
+        Ok(actors) => negotiate::respond(&req, &actors),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
+
+// GET /actors/{id}
+#[utoipa::path(
+    get,
+    path = "/actors/{id}",
+    responses(
+        (status = 200, description = "Actor (schema.org/Person con Accept: application/ld+json)", body = actor::Model,
+            headers(("ETag" = String, description = "Versión del actor y formato de la respuesta"))),
+        (status = 304, description = "Sin cambios desde el ETag de `If-None-Match`"),
+        (status = 404, description = "Actor not found"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    params(
+        ("id" = i32, Path, description = "Id del actor"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag de una copia anterior; si sigue vigente, `304`")
+    )
+)]
+pub async fn get_actor(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    id: web::Path<i32>,
+) -> impl Responder {
+    let model = match actor::Entity::find_by_id(id.into_inner())
+        .filter(actor::Column::DeletedAt.is_null())
+        .one(db.get_ref())
+        .await
+    {
+        Ok(Some(model)) => model,
+        Ok(None) => return HttpResponse::NotFound().body("Actor not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    };
+    let version = etag::version_tag(model.version);
+    let response = if jsonld::wants_jsonld(&req) {
+        HttpResponse::Ok()
+            .content_type(JSONLD_CONTENT_TYPE)
+            .json(jsonld::Person::from(model))
+    } else {
+        negotiate::respond(&req, &model)
+    };
+    etag::conditional_get(&req, &version, response)
+}
+
+// PUT /actors/{id}
+#[utoipa::path(
+    put,
+    path = "/actors/{id}",
+    request_body = CreateActor,
+    responses(
+        (status = 200, description = "Actor updated", body = actor::Model,
+            headers(("ETag" = String, description = "Versión del actor y formato de la respuesta"))),
+        (status = 403, description = "Requiere rol editor", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Actor not found"),
+        (status = 412, description = "El actor ha cambiado desde el ETag de `If-Match`", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    params(
+        ("id" = i32, Path, description = "Id del actor"),
+        ("If-Match" = Option<String>, Header, description = "ETag del actor; si no coincide, `412`")
+    )
+)]
+pub async fn update_actor(
+    _auth: Authorized<require::Editor>,
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    id: web::Path<i32>,
+    form: web::Json<CreateActor>,
+) -> impl Responder {
+    let model = match actor::Entity::find_by_id(id.into_inner())
+        .filter(actor::Column::DeletedAt.is_null())
+        .one(db.get_ref())
+        .await
+    {
+        Ok(Some(model)) => model,
+        Ok(None) => return HttpResponse::NotFound().body("Actor not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    };
+    if let Err(response) = etag::check_if_match(&req, &etag::version_tag(model.version)) {
+        return response;
+    }
+    let mut active: actor::ActiveModel = model.clone().into();
+    active.name = Set(form.name.clone());
+    let result = db
+        .transaction::<_, _, DbErr>(|txn| {
+            Box::pin(async move { audit::update(txn, &model, active).await })
+        })
+        .await;
+    match result {
+        Ok(model) => {
+            let version = etag::version_tag(model.version);
+            etag::with_tag(HttpResponse::Ok().json(model), &version)
+        }
+        Err(e) if etag::is_stale(&e) => etag::changed_concurrently(),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
+
+// DELETE /actors/{id}
+#[utoipa::path(
+    delete,
+    path = "/actors/{id}",
+    responses(
+        (status = 204, description = "Actor enviado a la papelera (deja de aparecer en los repartos)"),
+        (status = 403, description = "Requiere rol editor", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Actor not found"),
+        (status = 412, description = "El actor ha cambiado desde el ETag de `If-Match`", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    params(
+        ("id" = i32, Path, description = "Id del actor"),
+        ("If-Match" = Option<String>, Header, description = "ETag del actor; si no coincide, `412`")
     )
 )]
-pub async fn list_actors(db: web::Data<DatabaseConnection>) -> impl Responder {
-    match actor::Entity::find().all(db.get_ref()).await {
-        Ok(actors) => HttpResponse::Ok().json(actors),
+pub async fn delete_actor(
+    _auth: Authorized<require::Editor>,
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    id: web::Path<i32>,
+) -> impl Responder {
+    let current = match actor::Entity::find_by_id(id.into_inner())
+        .filter(actor::Column::DeletedAt.is_null())
+        .one(db.get_ref())
+        .await
+    {
+        Ok(Some(model)) => model,
+        Ok(None) => return HttpResponse::NotFound().body("Actor not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    };
+    if let Err(response) = etag::check_if_match(&req, &etag::version_tag(current.version)) {
+        return response;
+    }
+    // Se conservan las relaciones para poder restaurarlo tal como estaba.
+    match soft_delete::<actor::Entity>(
+        db.get_ref(),
+        [
+            actor::Column::Id,
+            actor::Column::DeletedAt,
+            actor::Column::Version,
+            actor::Column::UpdatedAt,
+            actor::Column::UpdatedBy,
+        ],
+        current.id,
+        current.version,
+    )
+    .await
+    {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => etag::changed_concurrently(),
         Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
     }
 }
@@ -116,10 +541,12 @@ pub struct CreateGenre {
     request_body = CreateGenre,
     responses(
         (status = 201, description = "Genre created", body = genre::Model),
+        (status = 403, description = "Requiere rol contributor", body = ProblemDetails, content_type = "application/problem+json"),
         (status = 500, description = "Internal Server Error")
     )
 )]
 pub async fn add_genre(
+    _auth: Authorized<require::Contributor>,
     db: web::Data<DatabaseConnection>,
     form: web::Json<CreateGenre>,
 ) -> impl Responder {
@@ -128,7 +555,12 @@ pub async fn add_genre(
         ..Default::default()
     };
 
-    match genre.insert(db.get_ref()).await {
+    let result = db
+        .transaction::<_, genre::Model, DbErr>(|txn| {
+            Box::pin(async move { audit::insert(txn, genre).await })
+        })
+        .await;
+    match result {
         Ok(model) => HttpResponse::Created().json(model),
         Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
     }
@@ -139,12 +571,136 @@ pub async fn add_genre(
     path = "/genres",
     responses(
         (status = 200, description = "List of genres", body = Vec<genre::Model>),
+        (status = 400, description = "Fecha mal formada"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    params(ChangedSince)
+)]
+pub async fn list_genres(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    since: web::Query<ChangedSince>,
+) -> impl Responder {
+    let since = match since.condition(genre::Column::CreatedAt, genre::Column::UpdatedAt) {
+        Ok(condition) => condition,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    match genre::Entity::find()
+        .filter(genre::Column::DeletedAt.is_null())
+        .filter(since)
+        .all(db.get_ref())
+        .await
+    {
+        Ok(genres) => negotiate::respond(&req, &genres),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
+
+// PUT /genres/{id}
+#[utoipa::path(
+    put,
+    path = "/genres/{id}",
+    request_body = CreateGenre,
+    responses(
+        (status = 200, description = "Genre updated", body = genre::Model,
+            headers(("ETag" = String, description = "Versión del género y formato de la respuesta"))),
+        (status = 403, description = "Requiere rol editor", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Genre not found"),
+        (status = 412, description = "El género ha cambiado desde el ETag de `If-Match`", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    params(
+        ("id" = i32, Path, description = "Id del genre"),
+        ("If-Match" = Option<String>, Header, description = "ETag del género; si no coincide, `412`")
+    )
+)]
+pub async fn update_genre(
+    _auth: Authorized<require::Editor>,
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    id: web::Path<i32>,
+    form: web::Json<CreateGenre>,
+) -> impl Responder {
+    let model = match genre::Entity::find_by_id(id.into_inner())
+        .filter(genre::Column::DeletedAt.is_null())
+        .one(db.get_ref())
+        .await
+    {
+        Ok(Some(model)) => model,
+        Ok(None) => return HttpResponse::NotFound().body("Genre not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    };
+    if let Err(response) = etag::check_if_match(&req, &etag::version_tag(model.version)) {
+        return response;
+    }
+    let mut active: genre::ActiveModel = model.clone().into();
+    active.name = Set(form.name.clone());
+    let result = db
+        .transaction::<_, _, DbErr>(|txn| {
+            Box::pin(async move { audit::update(txn, &model, active).await })
+        })
+        .await;
+    match result {
+        Ok(model) => {
+            let version = etag::version_tag(model.version);
+            etag::with_tag(HttpResponse::Ok().json(model), &version)
+        }
+        Err(e) if etag::is_stale(&e) => etag::changed_concurrently(),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
+
+// DELETE /genres/{id}
+#[utoipa::path(
+    delete,
+    path = "/genres/{id}",
+    responses(
+        (status = 204, description = "Genre enviado a la papelera (deja de aparecer en las películas)"),
+        (status = 403, description = "Requiere rol editor", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Genre not found"),
+        (status = 412, description = "El género ha cambiado desde el ETag de `If-Match`", body = ProblemDetails, content_type = "application/problem+json"),
         (status = 500, description = "Internal Server Error")
+    ),
+    params(
+        ("id" = i32, Path, description = "Id del genre"),
+        ("If-Match" = Option<String>, Header, description = "ETag del género; si no coincide, `412`")
     )
 )]
-pub async fn list_genres(db: web::Data<DatabaseConnection>) -> impl Responder {
-    match genre::Entity::find().all(db.get_ref()).await {
-        Ok(genres) => HttpResponse::Ok().json(genres),
+pub async fn delete_genre(
+    _auth: Authorized<require::Editor>,
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    id: web::Path<i32>,
+) -> impl Responder {
+    let current = match genre::Entity::find_by_id(id.into_inner())
+        .filter(genre::Column::DeletedAt.is_null())
+        .one(db.get_ref())
+        .await
+    {
+        Ok(Some(model)) => model,
+        Ok(None) => return HttpResponse::NotFound().body("Genre not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    };
+    if let Err(response) = etag::check_if_match(&req, &etag::version_tag(current.version)) {
+        return response;
+    }
+    // Se conservan las relaciones para poder restaurarlo tal como estaba.
+    match soft_delete::<genre::Entity>(
+        db.get_ref(),
+        [
+            genre::Column::Id,
+            genre::Column::DeletedAt,
+            genre::Column::Version,
+            genre::Column::UpdatedAt,
+            genre::Column::UpdatedBy,
+        ],
+        current.id,
+        current.version,
+    )
+    .await
+    {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => etag::changed_concurrently(),
         Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
     }
 }
@@ -157,6 +713,7 @@ pub async fn list_genres(db: web::Data<DatabaseConnection>) -> impl Responder {
 pub struct CreateMovie {
     pub title: String,
     pub director_id: i32,
+    pub year: Option<i32>,
     pub actor_ids: Vec<i32>,
     pub genre_ids: Vec<i32>,
 }
@@ -167,16 +724,79 @@ pub struct CreateMovie {
     path = "/movies",
     responses(
         (status = 200, description = "List of movies", body = Vec<movie::Model>),
+        (status = 400, description = "Fecha mal formada"),
         (status = 500, description = "Internal Server Error")
-    )
+    ),
+    params(ChangedSince)
 )]
-pub async fn list_movies(db: web::Data<DatabaseConnection>) -> impl Responder {
-    match movie::Entity::find().all(db.get_ref()).await {
-        Ok(movies) => HttpResponse::Ok().json(movies),
+pub async fn list_movies(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    since: web::Query<ChangedSince>,
+) -> impl Responder {
+    let since = match since.condition(movie::Column::CreatedAt, movie::Column::UpdatedAt) {
+        Ok(condition) => condition,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    match movie::Entity::find()
+        .filter(movie::Column::DeletedAt.is_null())
+        .filter(since)
+        .all(db.get_ref())
+        .await
+    {
+        Ok(movies) => negotiate::respond(&req, &movies),
         Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
     }
 }
 
+// GET /movies/{id}
+#[utoipa::path(
+    get,
+    path = "/movies/{id}",
+    responses(
+        (status = 200, description = "Película con director, actores y géneros (schema.org/Movie con Accept: application/ld+json)", body = MovieFull,
+            headers(("ETag" = String, description = "Versión de la ficha de la película y formato de la respuesta"))),
+        (status = 304, description = "Sin cambios desde el ETag de `If-None-Match`"),
+        (status = 404, description = "Movie not found"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    params(
+        ("id" = i32, Path, description = "Id de la película"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag de una copia anterior; si sigue vigente, `304`")
+    )
+)]
+pub async fn get_movie(
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    id: web::Path<i32>,
+) -> impl Responder {
+    let movie = match movie::Entity::find_by_id(id.into_inner())
+        .filter(movie::Column::DeletedAt.is_null())
+        .one(db.get_ref())
+        .await
+    {
+        Ok(Some(movie)) => movie,
+        Ok(None) => return HttpResponse::NotFound().body("Movie not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    };
+    let external_id = movie.external_id.clone();
+
+    let full = match load_movies_full(db.get_ref(), vec![movie]).await {
+        Ok(mut movies) => movies.remove(0),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    };
+    let version = etag::movie_tag(&full);
+
+    let response = if jsonld::wants_jsonld(&req) {
+        HttpResponse::Ok()
+            .content_type(JSONLD_CONTENT_TYPE)
+            .json(jsonld::Movie::new(full, external_id))
+    } else {
+        negotiate::respond(&req, &full)
+    };
+    etag::conditional_get(&req, &version, response)
+}
+
 // POST /movies (incluyendo relaciones)
 #[utoipa::path(
     post,
@@ -185,75 +805,330 @@ pub async fn list_movies(db: web::Data<DatabaseConnection>) -> impl Responder {
     responses(
         (status = 201, description = "Movie created", body = movie::Model),
         (status = 400, description = "Bad Request"),
+        (status = 403, description = "Requiere rol contributor", body = ProblemDetails, content_type = "application/problem+json"),
         (status = 500, description = "Internal Server Error")
     )
 )]
 pub async fn add_movie(
+    _auth: Authorized<require::Contributor>,
+    db: web::Data<DatabaseConnection>,
+    form: web::Json<CreateMovie>,
+) -> impl Responder {
+    match form.validate(db.get_ref()).await {
+        Ok(()) => {}
+        Err(ValidationError::Invalid(msg)) => return HttpResponse::BadRequest().body(msg),
+        Err(ValidationError::Db(e)) => {
+            return HttpResponse::InternalServerError().body(format!("DB error: {}", e));
+        }
+    }
+
+    let form = form.into_inner();
+    let result = db
+        .transaction::<_, movie::Model, DbErr>(|txn| {
+            Box::pin(async move { form.insert(txn).await })
+        })
+        .await;
+    match result {
+        Ok(created_movie) => HttpResponse::Created().json(created_movie),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
+
+// PUT /movies/{id} (incluyendo relaciones)
+#[utoipa::path(
+    put,
+    path = "/movies/{id}",
+    request_body = CreateMovie,
+    responses(
+        (status = 200, description = "Movie updated", body = movie::Model,
+            headers(("ETag" = String, description = "Versión de la ficha de la película y formato de la respuesta"))),
+        (status = 400, description = "Bad Request"),
+        (status = 403, description = "Requiere rol editor", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Movie not found"),
+        (status = 412, description = "La ficha de la película ha cambiado desde el ETag de `If-Match`", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    params(
+        ("id" = i32, Path, description = "Id de la película"),
+        ("If-Match" = Option<String>, Header, description = "ETag de la ficha de la película; si no coincide, `412`")
+    )
+)]
+pub async fn update_movie(
+    _auth: Authorized<require::Editor>,
     db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    id: web::Path<i32>,
     form: web::Json<CreateMovie>,
 ) -> impl Responder {
-    let director_exists = director::Entity::find_by_id(form.director_id)
+    let existing = match movie::Entity::find_by_id(id.into_inner())
+        .filter(movie::Column::DeletedAt.is_null())
         .one(db.get_ref())
+        .await
+    {
+        Ok(Some(movie)) => movie,
+        Ok(None) => return HttpResponse::NotFound().body("Movie not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    };
+    if let Err(response) = check_movie_if_match(db.get_ref(), &req, &existing).await {
+        return response;
+    }
+
+    match form.validate(db.get_ref()).await {
+        Ok(()) => {}
+        Err(ValidationError::Invalid(msg)) => return HttpResponse::BadRequest().body(msg),
+        Err(ValidationError::Db(e)) => {
+            return HttpResponse::InternalServerError().body(format!("DB error: {}", e));
+        }
+    }
+
+    let form = form.into_inner();
+    let result = db
+        .transaction::<_, movie::Model, DbErr>(|txn| {
+            Box::pin(async move { form.update(txn, existing).await })
+        })
         .await;
+    match result {
+        Ok(movie) => movie_response(db.get_ref(), movie).await,
+        Err(e) if etag::is_stale(&e) => etag::changed_concurrently(),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
 
-    match director_exists {
-        Ok(Some(_)) => {}
-        Ok(None) => return HttpResponse::BadRequest().body("Director does not exist"),
-        Err(e) => return HttpResponse::InternalServerError().body(format!("DB error: {}", e)),
+/// `Err` con la respuesta (`412` o `500`) si el `If-Match` de la petición no
+/// coincide con el ETag de la ficha de `movie`.
+pub async fn check_movie_if_match(
+    db: &DatabaseConnection,
+    req: &HttpRequest,
+    movie: &movie::Model,
+) -> Result<(), HttpResponse> {
+    if !req.headers().contains_key(header::IF_MATCH) {
+        return Ok(());
     }
+    match load_movies_full(db, vec![movie.clone()]).await {
+        Ok(mut movies) => etag::check_if_match(req, &etag::movie_tag(&movies.remove(0))),
+        Err(e) => Err(HttpResponse::InternalServerError().body(format!("Error: {}", e))),
+    }
+}
 
-    let movie = movie::ActiveModel {
-        title: Set(form.title.clone()),
-        director_id: Set(form.director_id),
-        ..Default::default()
-    };
+/// `200` con `movie` y el ETag de su ficha, para encadenar otra modificación.
+pub async fn movie_response(db: &DatabaseConnection, movie: movie::Model) -> HttpResponse {
+    match load_movies_full(db, vec![movie.clone()]).await {
+        Ok(mut movies) => etag::with_tag(
+            HttpResponse::Ok().json(movie),
+            &etag::movie_tag(&movies.remove(0)),
+        ),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
 
-    let created_movie = match movie.insert(db.get_ref()).await {
-        Ok(model) => model,
+// DELETE /movies/{id}
+#[utoipa::path(
+    delete,
+    path = "/movies/{id}",
+    responses(
+        (status = 204, description = "Movie enviada a la papelera"),
+        (status = 403, description = "Requiere rol editor", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "Movie not found"),
+        (status = 412, description = "La ficha de la película ha cambiado desde el ETag de `If-Match`", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    params(
+        ("id" = i32, Path, description = "Id de la película"),
+        ("If-Match" = Option<String>, Header, description = "ETag de la ficha de la película; si no coincide, `412`")
+    )
+)]
+pub async fn delete_movie(
+    _auth: Authorized<require::Editor>,
+    db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
+    id: web::Path<i32>,
+) -> impl Responder {
+    let current = match movie::Entity::find_by_id(id.into_inner())
+        .filter(movie::Column::DeletedAt.is_null())
+        .one(db.get_ref())
+        .await
+    {
+        Ok(Some(movie)) => movie,
+        Ok(None) => return HttpResponse::NotFound().body("Movie not found"),
         Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
     };
+    if let Err(response) = check_movie_if_match(db.get_ref(), &req, &current).await {
+        return response;
+    }
+    // Se conservan las relaciones para poder restaurarlo tal como estaba.
+    match soft_delete::<movie::Entity>(
+        db.get_ref(),
+        [
+            movie::Column::Id,
+            movie::Column::DeletedAt,
+            movie::Column::Version,
+            movie::Column::UpdatedAt,
+            movie::Column::UpdatedBy,
+        ],
+        current.id,
+        current.version,
+    )
+    .await
+    {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => etag::changed_concurrently(),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
 
-    for &actor_id in &form.actor_ids {
-        let actor_exists = actor::Entity::find_by_id(actor_id).one(db.get_ref()).await;
-        match actor_exists {
-            Ok(Some(_)) => {}
-            Ok(None) => {
-                return HttpResponse::BadRequest()
-                    .body(format!("Actor id {} does not exist", actor_id));
+pub enum ValidationError {
+    Invalid(String),
+    Db(DbErr),
+}
+
+impl From<DbErr> for ValidationError {
+    fn from(e: DbErr) -> Self {
+        ValidationError::Db(e)
+    }
+}
+
+impl CreateMovie {
+    /// Comprueba que existen (fuera de la papelera) el director, los actores y los
+    /// géneros indicados.
+    pub async fn validate<C: ConnectionTrait>(&self, db: &C) -> Result<(), ValidationError> {
+        if director::Entity::find_by_id(self.director_id)
+            .filter(director::Column::DeletedAt.is_null())
+            .one(db)
+            .await?
+            .is_none()
+        {
+            return Err(ValidationError::Invalid(
+                "Director does not exist".to_string(),
+            ));
+        }
+        for &actor_id in &self.actor_ids {
+            if actor::Entity::find_by_id(actor_id)
+                .filter(actor::Column::DeletedAt.is_null())
+                .one(db)
+                .await?
+                .is_none()
+            {
+                return Err(ValidationError::Invalid(format!(
+                    "Actor id {} does not exist",
+                    actor_id
+                )));
             }
-            Err(e) => return HttpResponse::InternalServerError().body(format!("DB error: {}", e)),
         }
+        for &genre_id in &self.genre_ids {
+            if genre::Entity::find_by_id(genre_id)
+                .filter(genre::Column::DeletedAt.is_null())
+                .one(db)
+                .await?
+                .is_none()
+            {
+                return Err(ValidationError::Invalid(format!(
+                    "Genre id {} does not exist",
+                    genre_id
+                )));
+            }
+        }
+        Ok(())
+    }
 
-        let movie_actor_rel = movie_actor::ActiveModel {
-            movie_id: Set(created_movie.id),
-            actor_id: Set(actor_id),
+    /// Inserta la película y sus relaciones, y guarda la primera revisión; llamar
+    /// antes a `validate`.
+    pub async fn insert<C: ConnectionTrait>(&self, db: &C) -> Result<movie::Model, DbErr> {
+        let movie = movie::ActiveModel {
+            title: Set(self.title.clone()),
+            director_id: Set(self.director_id),
+            year: Set(self.year),
+            ..Default::default()
         };
-        if let Err(e) = movie_actor_rel.insert(db.get_ref()).await {
-            return HttpResponse::InternalServerError().body(format!("Error movie-actor: {}", e));
-        }
+        let created_movie = audit::insert(db, movie).await?;
+        self.sync_relations(db, created_movie.id).await?;
+        revisions::record(db, &created_movie, RevisionAction::Create, None).await?;
+        Ok(created_movie)
+    }
+
+    /// Sustituye los datos y las relaciones de `movie` y guarda la revisión;
+    /// llamar antes a `validate`.
+    pub async fn update<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        movie: movie::Model,
+    ) -> Result<movie::Model, DbErr> {
+        self.apply(db, movie, RevisionAction::Update, None).await
     }
 
-    for &genre_id in &form.genre_ids {
-        let genre_exists = genre::Entity::find_by_id(genre_id).one(db.get_ref()).await;
-        match genre_exists {
-            Ok(Some(_)) => {}
-            Ok(None) => {
-                return HttpResponse::BadRequest()
-                    .body(format!("Genre id {} does not exist", genre_id));
+    /// Como `update`, pero la revisión se guarda como `action` (p. ej. `revert`).
+    pub async fn apply<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        movie: movie::Model,
+        action: RevisionAction,
+        reverted_from: Option<i32>,
+    ) -> Result<movie::Model, DbErr> {
+        revisions::ensure_baseline(db, &movie).await?;
+        let mut active: movie::ActiveModel = movie.clone().into();
+        active.title = Set(self.title.clone());
+        active.director_id = Set(self.director_id);
+        active.year = Set(self.year);
+        let updated = audit::update(db, &movie, active).await?;
+
+        self.sync_relations(db, updated.id).await?;
+        revisions::record(db, &updated, action, reverted_from).await?;
+        Ok(updated)
+    }
+
+    /// Deja el reparto y los géneros de la película `movie_id` como en el
+    /// formulario: borra las relaciones que sobran y crea las que faltan.
+    async fn sync_relations<C: ConnectionTrait>(&self, db: &C, movie_id: i32) -> Result<(), DbErr> {
+        let cast = movie_actor::Entity::find()
+            .filter(movie_actor::Column::MovieId.eq(movie_id))
+            .all(db)
+            .await?;
+        let mut actor_ids = Vec::new();
+        for row in cast {
+            if self.actor_ids.contains(&row.actor_id) {
+                actor_ids.push(row.actor_id);
+            } else {
+                row.clone().delete(db).await?;
+                audit::record(db, AuditAction::Purge, Some(&row), None).await?;
             }
-            Err(e) => return HttpResponse::InternalServerError().body(format!("DB error: {}", e)),
+        }
+        for &actor_id in &self.actor_ids {
+            if actor_ids.contains(&actor_id) {
+                continue;
+            }
+            let movie_actor_rel = movie_actor::ActiveModel {
+                movie_id: Set(movie_id),
+                actor_id: Set(actor_id),
+            };
+            audit::insert(db, movie_actor_rel).await?;
+            actor_ids.push(actor_id);
         }
 
-        let movie_genre_rel = movie_genre::ActiveModel {
-            movie_id: Set(created_movie.id),
-            genre_id: Set(genre_id),
-        };
-        if let Err(e) = movie_genre_rel.insert(db.get_ref()).await {
-            return HttpResponse::InternalServerError().body(format!("Error movie-genre: {}", e));
+        let tags = movie_genre::Entity::find()
+            .filter(movie_genre::Column::MovieId.eq(movie_id))
+            .all(db)
+            .await?;
+        let mut genre_ids = Vec::new();
+        for row in tags {
+            if self.genre_ids.contains(&row.genre_id) {
+                genre_ids.push(row.genre_id);
+            } else {
+                row.clone().delete(db).await?;
+                audit::record(db, AuditAction::Purge, Some(&row), None).await?;
+            }
+        }
+        for &genre_id in &self.genre_ids {
+            if genre_ids.contains(&genre_id) {
+                continue;
+            }
+            let movie_genre_rel = movie_genre::ActiveModel {
+                movie_id: Set(movie_id),
+                genre_id: Set(genre_id),
+            };
+            audit::insert(db, movie_genre_rel).await?;
+            genre_ids.push(genre_id);
         }
+        Ok(())
     }
-
-    HttpResponse::Created().json(created_movie)
 }
 
 #[derive(Deserialize, ToSchema)]
@@ -261,15 +1136,22 @@ pub struct MovieFullQuery {
     q: Option<String>,
     page: Option<u32>,
     per_page: Option<u32>,
+    facets: Option<String>,
 }
 
 #[derive(Serialize, ToSchema)]
 pub struct MovieFull {
     pub id: i32,
     pub title: String,
+    pub year: Option<i32>,
     pub director: Option<director::Model>,
     pub actors: Vec<actor::Model>,
     pub genres: Vec<genre::Model>,
+    pub created_at: Option<DateTimeUtc>,
+    pub updated_at: Option<DateTimeUtc>,
+    pub created_by: Option<String>,
+    pub updated_by: Option<String>,
+    pub version: i32,
 }
 
 #[derive(Serialize, ToSchema)]
@@ -284,6 +1166,134 @@ pub struct Meta {
 pub struct MovieFullResponse {
     pub meta: Meta,
     pub results: Vec<MovieFull>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub facets: Option<Facets>,
+}
+
+/// Ids por consulta en `find_in`, por debajo del límite de variables de SQLite.
+const MAX_IN_IDS: usize = 500;
+
+/// Filas de `E` que cumplen `condition` y cuyo `column` está en `ids`. Se
+/// consultan en tandas de `MAX_IN_IDS` ids distintos.
+async fn find_in<C, E>(
+    db: &C,
+    column: E::Column,
+    ids: impl IntoIterator<Item = i32>,
+    condition: Condition,
+) -> Result<Vec<E::Model>, DbErr>
+where
+    C: ConnectionTrait,
+    E: EntityTrait,
+{
+    let ids: Vec<i32> = ids
+        .into_iter()
+        .collect::<std::collections::BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let mut rows = Vec::new();
+    for chunk in ids.chunks(MAX_IN_IDS) {
+        rows.extend(
+            E::find()
+                .filter(column.is_in(chunk.iter().copied()))
+                .filter(condition.clone())
+                .all(db)
+                .await?,
+        );
+    }
+    Ok(rows)
+}
+
+/// Carga director, actores y géneros de `movies` con una consulta por tabla, en
+/// lugar de varias por película. Los actores y géneros de la papelera se omiten.
+pub async fn load_movies_full<C: ConnectionTrait>(
+    db: &C,
+    movies: Vec<movie::Model>,
+) -> Result<Vec<MovieFull>, DbErr> {
+    use std::collections::HashMap;
+
+    let movie_ids: Vec<i32> = movies.iter().map(|m| m.id).collect();
+    let director_ids: Vec<i32> = movies.iter().map(|m| m.director_id).collect();
+
+    let directors: HashMap<i32, director::Model> =
+        find_in::<_, director::Entity>(db, director::Column::Id, director_ids, Condition::all())
+            .await?
+            .into_iter()
+            .map(|d| (d.id, d))
+            .collect();
+
+    let movie_actors = find_in::<_, movie_actor::Entity>(
+        db,
+        movie_actor::Column::MovieId,
+        movie_ids.clone(),
+        Condition::all(),
+    )
+    .await?;
+    let actors: HashMap<i32, actor::Model> = find_in::<_, actor::Entity>(
+        db,
+        actor::Column::Id,
+        movie_actors.iter().map(|ma| ma.actor_id),
+        Condition::all().add(actor::Column::DeletedAt.is_null()),
+    )
+    .await?
+    .into_iter()
+    .map(|a| (a.id, a))
+    .collect();
+
+    let movie_genres = find_in::<_, movie_genre::Entity>(
+        db,
+        movie_genre::Column::MovieId,
+        movie_ids,
+        Condition::all(),
+    )
+    .await?;
+    let genres: HashMap<i32, genre::Model> = find_in::<_, genre::Entity>(
+        db,
+        genre::Column::Id,
+        movie_genres.iter().map(|mg| mg.genre_id),
+        Condition::all().add(genre::Column::DeletedAt.is_null()),
+    )
+    .await?
+    .into_iter()
+    .map(|g| (g.id, g))
+    .collect();
+
+    let mut cast: HashMap<i32, Vec<i32>> = HashMap::new();
+    for ma in &movie_actors {
+        cast.entry(ma.movie_id).or_default().push(ma.actor_id);
+    }
+    let mut tags: HashMap<i32, Vec<i32>> = HashMap::new();
+    for mg in &movie_genres {
+        tags.entry(mg.movie_id).or_default().push(mg.genre_id);
+    }
+
+    Ok(movies
+        .into_iter()
+        .map(|mov| {
+            let mut actor_ids = cast.remove(&mov.id).unwrap_or_default();
+            actor_ids.sort_unstable();
+            let mut genre_ids = tags.remove(&mov.id).unwrap_or_default();
+            genre_ids.sort_unstable();
+            MovieFull {
+                id: mov.id,
+                title: mov.title,
+                year: mov.year,
+                director: directors.get(&mov.director_id).cloned(),
+                actors: actor_ids
+                    .iter()
+                    .filter_map(|id| actors.get(id).cloned())
+                    .collect(),
+                genres: genre_ids
+                    .iter()
+                    .filter_map(|id| genres.get(id).cloned())
+                    .collect(),
+                created_at: mov.created_at,
+                updated_at: mov.updated_at,
+                created_by: mov.created_by,
+                updated_by: mov.updated_by,
+                version: mov.version,
+            }
+        })
+        .collect())
 }
 
 // GET /movies/full
@@ -291,108 +1301,49 @@ pub struct MovieFullResponse {
     get,
     path = "/movies/full",
     responses(
-        (status = 200, description = "Lista de películas full", body = MovieFullResponse)
+        (status = 200, description = "Lista de películas full", body = MovieFullResponse),
+        (status = 400, description = "Consulta o fecha mal formada, indicando la posición del error"),
+        (status = 500, description = "Internal Server Error")
     ),
     params(
-        ("q" = Option<String>, Query, description = "Texto de búsqueda"),
+        ("q" = Option<String>, Query, description = "Búsqueda: términos libres, campos (title:, director:, actor:, genre:, year:1990..1999), \"frases\", -negación, OR y paréntesis. Ej: genre:drama director:\"ridley scott\" -genre:horror"),
         ("page" = Option<u32>, Query, description = "Página"),
-        ("per_page" = Option<u32>, Query, description = "Resultados por página")
+        ("per_page" = Option<u32>, Query, description = "Resultados por página"),
+        ("facets" = Option<String>, Query, description = "Facetas a calcular sobre todo el resultado: genre,director,actor,decade"),
+        ChangedSince
     )
 )]
 pub async fn list_movies_full(
     db: web::Data<DatabaseConnection>,
+    req: HttpRequest,
     query: web::Query<MovieFullQuery>,
+    since: web::Query<ChangedSince>,
 ) -> impl Responder {
-    use std::collections::HashSet;
-
     let page = query.page.unwrap_or(1).max(1);
-    let per_page = query.per_page.unwrap_or(10).max(1).min(100);
+    let per_page = query.per_page.unwrap_or(10).clamp(1, 100);
     let offset = (page - 1) * per_page;
 
-    let q = query
-        .q
-        .as_ref()
-        .map(|s| s.trim().to_lowercase())
-        .filter(|s| !s.is_empty());
-
-    let mut movie_ids = Vec::<i32>::new();
-
-    if let Some(q) = q.clone() {
-        let movies_by_title = movie::Entity::find()
-            .filter(movie::Column::Title.contains(&q))
-            .all(db.get_ref())
-            .await
-            .unwrap_or_default();
-        movie_ids.extend(movies_by_title.iter().map(|m| m.id));
-
-        let directors = director::Entity::find()
-            .filter(director::Column::Name.contains(&q))
-            .all(db.get_ref())
-            .await
-            .unwrap_or_default();
-        let director_ids: Vec<i32> = directors.iter().map(|d| d.id).collect();
-        if !director_ids.is_empty() {
-            let movies_by_director = movie::Entity::find()
-                .filter(movie::Column::DirectorId.is_in(director_ids.clone()))
-                .all(db.get_ref())
-                .await
-                .unwrap_or_default();
-            movie_ids.extend(movies_by_director.iter().map(|m| m.id));
-        }
-
-        let genres = genre::Entity::find()
-            .filter(genre::Column::Name.contains(&q))
-            .all(db.get_ref())
-            .await
-            .unwrap_or_default();
-        let genre_ids: Vec<i32> = genres.iter().map(|g| g.id).collect();
-        if !genre_ids.is_empty() {
-            let movie_genres = movie_genre::Entity::find()
-                .filter(movie_genre::Column::GenreId.is_in(genre_ids.clone()))
-                .all(db.get_ref())
-                .await
-                .unwrap_or_default();
-            movie_ids.extend(movie_genres.iter().map(|mg| mg.movie_id));
-        }
-
-        let actors = actor::Entity::find()
-            .filter(actor::Column::Name.contains(&q))
-            .all(db.get_ref())
-            .await
-            .unwrap_or_default();
-        let actor_ids: Vec<i32> = actors.iter().map(|a| a.id).collect();
-        if !actor_ids.is_empty() {
-            let movie_actors = movie_actor::Entity::find()
-                .filter(movie_actor::Column::ActorId.is_in(actor_ids.clone()))
-                .all(db.get_ref())
-                .await
-                .unwrap_or_default();
-            movie_ids.extend(movie_actors.iter().map(|ma| ma.movie_id));
+    let mut movies_query = match since.condition(movie::Column::CreatedAt, movie::Column::UpdatedAt)
+    {
+        Ok(since) => movie::Entity::find()
+            .filter(movie::Column::DeletedAt.is_null())
+            .filter(since),
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    if let Some(q) = query.q.as_deref().filter(|q| !q.trim().is_empty()) {
+        match search::parse(q) {
+            Ok(expr) => movies_query = movies_query.filter(search::condition(&expr)),
+            Err(e) => return HttpResponse::BadRequest().body(e.to_string()),
         }
     }
 
-    let movie_ids: Vec<i32> = {
-        let set: HashSet<_> = movie_ids.into_iter().collect();
-        set.into_iter().collect()
-    };
-
-    let mut movies_query = movie::Entity::find();
-    if query.q.is_some() {
-        if movie_ids.is_empty() {
-            let meta = Meta {
-                total: 0,
-                page,
-                per_page,
-                last_page: 1,
-            };
-            let response = MovieFullResponse {
-                meta,
-                results: Vec::new(),
-            };
-            return HttpResponse::Ok().json(response);
+    let facet_kinds = match query.facets.as_deref().map(facets::parse_kinds) {
+        None => Vec::new(),
+        Some(Ok(kinds)) => kinds,
+        Some(Err(name)) => {
+            return HttpResponse::BadRequest().body(format!("Unknown facet: {}", name));
         }
-        movies_query = movies_query.filter(movie::Column::Id.is_in(movie_ids));
-    }
+    };
 
     let total = match movies_query.clone().count(db.get_ref()).await {
         Ok(c) => c as usize,
@@ -402,6 +1353,7 @@ pub async fn list_movies_full(
     let last_page = ((total as f32) / (per_page as f32)).ceil().max(1.0) as u32;
 
     let movies = match movies_query
+        .clone()
         .order_by_asc(movie::Column::Id)
         .offset(Some(offset as u64))
         .limit(Some(per_page as u64))
@@ -412,61 +1364,19 @@ pub async fn list_movies_full(
         Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
     };
 
-    let mut result = Vec::new();
-
-    for mov in movies {
-        let director = director::Entity::find_by_id(mov.director_id)
-            .one(db.get_ref())
-            .await
-            .ok()
-            .flatten();
-
-        let actor_ids: Vec<i32> = movie_actor::Entity::find()
-            .filter(movie_actor::Column::MovieId.eq(mov.id))
-            .all(db.get_ref())
-            .await
-            .unwrap_or_default()
-            .into_iter()
-            .map(|ma| ma.actor_id)
-            .collect();
-
-        let actors = if !actor_ids.is_empty() {
-            actor::Entity::find()
-                .filter(actor::Column::Id.is_in(actor_ids))
-                .all(db.get_ref())
-                .await
-                .unwrap_or_default()
-        } else {
-            Vec::new()
-        };
-
-        let genre_ids: Vec<i32> = movie_genre::Entity::find()
-            .filter(movie_genre::Column::MovieId.eq(mov.id))
-            .all(db.get_ref())
-            .await
-            .unwrap_or_default()
-            .into_iter()
-            .map(|mg| mg.genre_id)
-            .collect();
-
-        let genres = if !genre_ids.is_empty() {
-            genre::Entity::find()
-                .filter(genre::Column::Id.is_in(genre_ids))
-                .all(db.get_ref())
-                .await
-                .unwrap_or_default()
-        } else {
-            Vec::new()
-        };
+    let result = match load_movies_full(db.get_ref(), movies).await {
+        Ok(result) => result,
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    };
 
-        result.push(MovieFull {
-            id: mov.id,
-            title: mov.title,
-            director,
-            actors,
-            genres,
-        });
-    }
+    let facets = if facet_kinds.is_empty() {
+        None
+    } else {
+        match facets::compute(db.get_ref(), &movies_query, &facet_kinds).await {
+            Ok(facets) => Some(facets),
+            Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+        }
+    };
 
     let meta = Meta {
         total,
@@ -478,7 +1388,8 @@ pub async fn list_movies_full(
     let response = MovieFullResponse {
         meta,
         results: result,
+        facets,
     };
 
-    HttpResponse::Ok().json(response)
+    negotiate::respond(&req, &response)
 }
@@ -0,0 +1,552 @@
+// Lenguaje de búsqueda de /movies/full:
+//
+//   genre:drama director:"ridley scott" actor:weaver -genre:horror
+//   (genre:comedy OR genre:drama) year:1990..1999
+//
+// Los términos separados por espacios se combinan con AND, `OR` (en mayúsculas)
+// separa alternativas, `-` niega el término o grupo que le sigue y los paréntesis
+// agrupan. Un término sin campo busca en título, director, actores y géneros.
+// Los directores, actores y géneros de la papelera no cuentan.
+
+use crate::models::{actor, director, genre, movie, movie_actor, movie_genre};
+use sea_orm::sea_query::{Expr, LikeExpr, SimpleExpr};
+use sea_orm::{ColumnTrait, Condition, EntityTrait, QueryFilter, QuerySelect, QueryTrait};
+use std::fmt;
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Field {
+    Title,
+    Director,
+    Actor,
+    Genre,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SearchExpr {
+    /// Texto a buscar en un campo, o en todos si `field` es `None`.
+    Text {
+        field: Option<Field>,
+        text: String,
+    },
+    /// `year:1979` o `year:1980..1989` (ambos extremos incluidos).
+    Year {
+        from: i32,
+        to: i32,
+    },
+    Not(Box<SearchExpr>),
+    And(Vec<SearchExpr>),
+    Or(Vec<SearchExpr>),
+}
+
+/// Error de sintaxis; `position` es el carácter (empezando en 1) donde se detectó.
+#[derive(Debug)]
+pub struct ParseError {
+    pub position: usize,
+    pub message: String,
+    query: String,
+}
+
+impl ParseError {
+    fn new(query: &str, pos: usize, message: impl Into<String>) -> Self {
+        ParseError {
+            position: pos + 1,
+            message: message.into(),
+            query: query.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Invalid query at position {}: {}",
+            self.position, self.message
+        )?;
+        writeln!(f, "  {}", self.query)?;
+        write!(f, "  {}^", " ".repeat(self.position - 1))
+    }
+}
+
+enum Token {
+    LParen,
+    RParen,
+    Or,
+    Not,
+    Term(SearchExpr),
+}
+
+struct Lexer<'a> {
+    query: &'a str,
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(query: &'a str) -> Self {
+        Lexer {
+            query,
+            chars: query.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn error(&self, pos: usize, message: impl Into<String>) -> ParseError {
+        ParseError::new(self.query, pos, message)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn tokens(mut self) -> Result<Vec<(usize, Token)>, ParseError> {
+        let mut tokens = Vec::new();
+        while let Some(c) = self.peek() {
+            let start = self.pos;
+            let token = match c {
+                c if c.is_whitespace() => {
+                    self.pos += 1;
+                    continue;
+                }
+                '(' => {
+                    self.pos += 1;
+                    Token::LParen
+                }
+                ')' => {
+                    self.pos += 1;
+                    Token::RParen
+                }
+                '-' => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some(n) if !n.is_whitespace() && n != ')' => Token::Not,
+                        _ => return Err(self.error(start, "'-' must be followed by a term")),
+                    }
+                }
+                '"' => Token::Term(SearchExpr::Text {
+                    field: None,
+                    text: self.quoted()?,
+                }),
+                _ => self.word_token(start)?,
+            };
+            tokens.push((start, token));
+        }
+        Ok(tokens)
+    }
+
+    fn word_token(&mut self, start: usize) -> Result<Token, ParseError> {
+        while self
+            .peek()
+            .is_some_and(|c| !c.is_whitespace() && !matches!(c, '(' | ')' | '"'))
+        {
+            self.pos += 1;
+        }
+        let word: String = self.chars[start..self.pos].iter().collect();
+        if word == "OR" {
+            return Ok(Token::Or);
+        }
+
+        // `campo:valor` solo si el campo existe; si no, el ':' es parte del texto
+        // (p. ej. "Alien: Covenant").
+        let Some((name, rest)) = word.split_once(':') else {
+            return Ok(Token::Term(SearchExpr::Text {
+                field: None,
+                text: word,
+            }));
+        };
+        let field = match name.to_lowercase().as_str() {
+            "title" => Some(Field::Title),
+            "director" => Some(Field::Director),
+            "actor" => Some(Field::Actor),
+            "genre" => Some(Field::Genre),
+            "year" => None,
+            _ => {
+                return Ok(Token::Term(SearchExpr::Text {
+                    field: None,
+                    text: word,
+                }));
+            }
+        };
+        let value = match rest {
+            "" if self.peek() == Some('"') => self.quoted()?,
+            "" => {
+                return Err(self.error(self.pos, format!("missing value for '{}:'", name)));
+            }
+            rest => rest.to_string(),
+        };
+
+        match field {
+            Some(field) => Ok(Token::Term(SearchExpr::Text {
+                field: Some(field),
+                text: value,
+            })),
+            None => {
+                let year = |s: &str| s.trim().parse::<i32>().ok();
+                let range = match value.split_once("..") {
+                    Some((from, to)) => year(from).zip(year(to)),
+                    None => year(&value).map(|y| (y, y)),
+                };
+                match range {
+                    Some((from, to)) if from <= to => {
+                        Ok(Token::Term(SearchExpr::Year { from, to }))
+                    }
+                    _ => Err(self.error(
+                        start + name.chars().count() + 1,
+                        format!("invalid year '{}', expected YYYY or YYYY..YYYY", value),
+                    )),
+                }
+            }
+        }
+    }
+
+    fn quoted(&mut self) -> Result<String, ParseError> {
+        let open = self.pos;
+        self.pos += 1;
+        while self.peek().is_some_and(|c| c != '"') {
+            self.pos += 1;
+        }
+        if self.peek().is_none() {
+            return Err(self.error(open, "unterminated quoted phrase"));
+        }
+        let phrase: String = self.chars[open + 1..self.pos].iter().collect();
+        self.pos += 1;
+        if phrase.trim().is_empty() {
+            return Err(self.error(open, "empty quoted phrase"));
+        }
+        Ok(phrase)
+    }
+}
+
+struct Parser<'a> {
+    query: &'a str,
+    tokens: Peekable<IntoIter<(usize, Token)>>,
+    end: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, pos: usize, message: impl Into<String>) -> ParseError {
+        ParseError::new(self.query, pos, message)
+    }
+
+    /// Posición del siguiente token, o el final del texto si no quedan.
+    fn position(&mut self) -> usize {
+        self.tokens.peek().map_or(self.end, |(p, _)| *p)
+    }
+
+    fn or_expr(&mut self) -> Result<SearchExpr, ParseError> {
+        let mut alternatives = vec![self.and_expr()?];
+        while self
+            .tokens
+            .next_if(|(_, t)| matches!(t, Token::Or))
+            .is_some()
+        {
+            alternatives.push(self.and_expr()?);
+        }
+        Ok(if alternatives.len() == 1 {
+            alternatives.remove(0)
+        } else {
+            SearchExpr::Or(alternatives)
+        })
+    }
+
+    fn and_expr(&mut self) -> Result<SearchExpr, ParseError> {
+        let mut terms = Vec::new();
+        while !matches!(
+            self.tokens.peek(),
+            None | Some((_, Token::Or)) | Some((_, Token::RParen))
+        ) {
+            terms.push(self.unary()?);
+        }
+        match terms.len() {
+            0 => {
+                let pos = self.position();
+                Err(self.error(pos, "expected a search term"))
+            }
+            1 => Ok(terms.remove(0)),
+            _ => Ok(SearchExpr::And(terms)),
+        }
+    }
+
+    fn unary(&mut self) -> Result<SearchExpr, ParseError> {
+        match self.tokens.next() {
+            Some((_, Token::Not)) => Ok(SearchExpr::Not(Box::new(self.unary()?))),
+            Some((open, Token::LParen)) => {
+                let inner = self.or_expr()?;
+                match self.tokens.next() {
+                    Some((_, Token::RParen)) => Ok(inner),
+                    _ => Err(self.error(open, "unclosed '('")),
+                }
+            }
+            Some((_, Token::Term(term))) => Ok(term),
+            Some((pos, _)) => Err(self.error(pos, "expected a search term")),
+            None => Err(self.error(self.end, "expected a search term")),
+        }
+    }
+}
+
+/// Analiza el texto de `q`.
+pub fn parse(query: &str) -> Result<SearchExpr, ParseError> {
+    let tokens = Lexer::new(query).tokens()?;
+    let mut parser = Parser {
+        query,
+        tokens: tokens.into_iter().peekable(),
+        end: query.chars().count(),
+    };
+    let expr = parser.or_expr()?;
+    // El único token que puede detener `or_expr` antes del final es un ')'.
+    if let Some((pos, _)) = parser.tokens.next() {
+        return Err(parser.error(pos, "unexpected ')'"));
+    }
+    Ok(expr)
+}
+
+/// Escapa los comodines de LIKE (`%`, `_` y el propio `\`).
+pub fn escape_like(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn contains<C: ColumnTrait>(column: C, text: &str) -> SimpleExpr {
+    let pattern = format!("%{}%", escape_like(text));
+    Expr::col((column.entity_name(), column)).like(LikeExpr::new(pattern).escape('\\'))
+}
+
+fn text_condition(field: Field, text: &str) -> SimpleExpr {
+    match field {
+        Field::Title => contains(movie::Column::Title, text),
+        Field::Director => movie::Column::DirectorId.in_subquery(
+            director::Entity::find()
+                .select_only()
+                .column(director::Column::Id)
+                .filter(contains(director::Column::Name, text))
+                .filter(director::Column::DeletedAt.is_null())
+                .into_query(),
+        ),
+        Field::Actor => movie::Column::Id.in_subquery(
+            movie_actor::Entity::find()
+                .select_only()
+                .column(movie_actor::Column::MovieId)
+                .filter(
+                    movie_actor::Column::ActorId.in_subquery(
+                        actor::Entity::find()
+                            .select_only()
+                            .column(actor::Column::Id)
+                            .filter(contains(actor::Column::Name, text))
+                            .filter(actor::Column::DeletedAt.is_null())
+                            .into_query(),
+                    ),
+                )
+                .into_query(),
+        ),
+        Field::Genre => movie::Column::Id.in_subquery(
+            movie_genre::Entity::find()
+                .select_only()
+                .column(movie_genre::Column::MovieId)
+                .filter(
+                    movie_genre::Column::GenreId.in_subquery(
+                        genre::Entity::find()
+                            .select_only()
+                            .column(genre::Column::Id)
+                            .filter(contains(genre::Column::Name, text))
+                            .filter(genre::Column::DeletedAt.is_null())
+                            .into_query(),
+                    ),
+                )
+                .into_query(),
+        ),
+    }
+}
+
+/// Traduce la expresión a una condición sobre `movies`.
+pub fn condition(expr: &SearchExpr) -> Condition {
+    match expr {
+        SearchExpr::Text {
+            field: Some(field),
+            text,
+        } => Condition::all().add(text_condition(*field, text)),
+        SearchExpr::Text { field: None, text } => {
+            [Field::Title, Field::Director, Field::Actor, Field::Genre]
+                .into_iter()
+                .fold(Condition::any(), |cond, field| {
+                    cond.add(text_condition(field, text))
+                })
+        }
+        SearchExpr::Year { from, to } => {
+            Condition::all().add(movie::Column::Year.between(*from, *to))
+        }
+        SearchExpr::Not(inner) => negated(inner),
+        SearchExpr::And(terms) => terms
+            .iter()
+            .fold(Condition::all(), |cond, t| cond.add(condition(t))),
+        SearchExpr::Or(terms) => terms
+            .iter()
+            .fold(Condition::any(), |cond, t| cond.add(condition(t))),
+    }
+}
+
+/// Condición de `-expr`. La negación se lleva hasta los términos para que
+/// `-year:…` incluya las películas sin año: `NOT (year BETWEEN …)` es nulo, y no
+/// verdadero, cuando `year` es `NULL`.
+fn negated(expr: &SearchExpr) -> Condition {
+    match expr {
+        SearchExpr::Year { from, to } => Condition::any()
+            .add(movie::Column::Year.not_between(*from, *to))
+            .add(movie::Column::Year.is_null()),
+        SearchExpr::Text { .. } => condition(expr).not(),
+        SearchExpr::Not(inner) => condition(inner),
+        SearchExpr::And(terms) => terms
+            .iter()
+            .fold(Condition::any(), |cond, t| cond.add(negated(t))),
+        SearchExpr::Or(terms) => terms
+            .iter()
+            .fold(Condition::all(), |cond, t| cond.add(negated(t))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::ActiveValue::Set;
+    use sea_orm::{
+        ActiveModelTrait, ConnectionTrait, Database, DatabaseConnection, QueryOrder, Schema,
+    };
+
+    fn text(field: Option<Field>, text: &str) -> SearchExpr {
+        SearchExpr::Text {
+            field,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_fields_phrases_and_operators() {
+        assert_eq!(
+            parse(r#"genre:drama director:"ridley scott" -genre:horror"#).unwrap(),
+            SearchExpr::And(vec![
+                text(Some(Field::Genre), "drama"),
+                text(Some(Field::Director), "ridley scott"),
+                SearchExpr::Not(Box::new(text(Some(Field::Genre), "horror"))),
+            ])
+        );
+        assert_eq!(
+            parse("(genre:comedy OR genre:drama) year:1990..1999").unwrap(),
+            SearchExpr::And(vec![
+                SearchExpr::Or(vec![
+                    text(Some(Field::Genre), "comedy"),
+                    text(Some(Field::Genre), "drama"),
+                ]),
+                SearchExpr::Year {
+                    from: 1990,
+                    to: 1999
+                },
+            ])
+        );
+        assert_eq!(
+            parse("year:1979").unwrap(),
+            SearchExpr::Year {
+                from: 1979,
+                to: 1979
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_fields_are_plain_text() {
+        assert_eq!(
+            parse("Alien: Covenant").unwrap(),
+            SearchExpr::And(vec![text(None, "Alien:"), text(None, "Covenant")])
+        );
+        assert_eq!(parse("or").unwrap(), text(None, "or"));
+    }
+
+    #[test]
+    fn reports_the_error_position() {
+        let error = |query: &str| {
+            let e = parse(query).unwrap_err();
+            (e.position, e.message)
+        };
+        assert_eq!(error("(genre:drama").0, 1);
+        assert_eq!(error("genre:drama)"), (12, "unexpected ')'".to_string()));
+        assert_eq!(error("year:199x").0, 6);
+        assert_eq!(error("year:1999..1990").0, 6);
+        assert_eq!(error("alien - ridley").0, 7);
+        assert_eq!(error(r#"title:"alien"#).0, 7);
+        assert_eq!(error("genre:").0, 7);
+        assert_eq!(error("alien OR").0, 9);
+    }
+
+    #[test]
+    fn escapes_like_wildcards() {
+        assert_eq!(escape_like(r"100%_a\b"), r"100\%\_a\\b");
+    }
+
+    /// Base de datos en memoria con la tabla `movies` y una película por año de
+    /// `years` (`None`: sin año).
+    async fn movies_db(years: &[Option<i32>]) -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let schema = Schema::new(db.get_database_backend());
+        db.execute(
+            db.get_database_backend()
+                .build(&schema.create_table_from_entity(movie::Entity)),
+        )
+        .await
+        .unwrap();
+        for (i, year) in years.iter().enumerate() {
+            movie::ActiveModel {
+                title: Set(format!("Movie {}", i + 1)),
+                director_id: Set(1),
+                year: Set(*year),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .unwrap();
+        }
+        db
+    }
+
+    /// Años de las películas que cumplen `query`, por id.
+    async fn years(db: &DatabaseConnection, query: &str) -> Vec<Option<i32>> {
+        movie::Entity::find()
+            .filter(condition(&parse(query).unwrap()))
+            .order_by_asc(movie::Column::Id)
+            .all(db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|m| m.year)
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn negated_years_keep_movies_without_year() {
+        let db = movies_db(&[Some(1979), Some(1995), None, Some(2000)]).await;
+
+        assert_eq!(years(&db, "year:1990..1999").await, [Some(1995)]);
+        assert_eq!(
+            years(&db, "-year:1990..1999").await,
+            [Some(1979), None, Some(2000)]
+        );
+        assert_eq!(years(&db, "--year:1990..1999").await, [Some(1995)]);
+        assert_eq!(
+            years(&db, "-(year:1990..1999 OR year:2000)").await,
+            [Some(1979), None]
+        );
+        assert_eq!(
+            years(&db, "-(year:1970..1999 year:1990..2010)").await,
+            [Some(1979), None, Some(2000)]
+        );
+        assert_eq!(
+            years(&db, r#"-title:"movie 1" -year:2000"#).await,
+            [Some(1995), None]
+        );
+    }
+}
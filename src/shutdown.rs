@@ -0,0 +1,210 @@
+// Apagado ordenado.
+//
+// Al recibir SIGTERM (o Ctrl+C) el servidor pasa a «drenando»: `/readyz` responde
+// `503` para que el balanceador deje de enviar tráfico, y tras `drain_seconds` deja
+// de aceptar conexiones. Las peticiones en curso tienen `timeout_seconds` para
+// terminar; las que siguen en marcha se cortan. Al final se cierra el pool de la
+// base de datos y se registra un resumen con las peticiones cortadas.
+
+use crate::config::ShutdownConfig;
+use crate::telemetry::RequestId;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpMessage, web};
+use sea_orm::DatabaseConnection;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Estado del servidor compartido entre los workers.
+#[derive(Default)]
+pub struct Lifecycle {
+    draining: AtomicBool,
+    next_id: AtomicU64,
+    /// Peticiones en curso: id interno -> descripción para el resumen.
+    in_flight: Mutex<HashMap<u64, String>>,
+    /// Peticiones terminadas después de empezar el drenaje.
+    drained: AtomicU64,
+}
+
+impl Lifecycle {
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    fn in_flight(&self) -> usize {
+        self.in_flight.lock().unwrap().len()
+    }
+}
+
+/// Registro de una petición en `Lifecycle::in_flight`. Se quita al soltarlo, tanto
+/// si la petición termina como si se descarta antes (el cliente cierra la
+/// conexión), salvo que se descarte sin terminar durante el drenaje: entonces se
+/// ha cortado y se deja para el resumen de `finish`.
+struct InFlight {
+    lifecycle: web::Data<Lifecycle>,
+    id: u64,
+    finished: bool,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if !self.finished && self.lifecycle.is_draining() {
+            return;
+        }
+        if let Ok(mut in_flight) = self.lifecycle.in_flight.lock() {
+            in_flight.remove(&self.id);
+        }
+    }
+}
+
+/// Middleware: registra cada petición mientras está en curso. Si el worker se
+/// detiene antes de que termine, la petición queda registrada y aparece como
+/// cortada en el resumen.
+pub async fn track_in_flight(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let Some(lifecycle) = req.app_data::<web::Data<Lifecycle>>().cloned() else {
+        return next.call(req).await;
+    };
+
+    let id = lifecycle.next_id.fetch_add(1, Ordering::Relaxed);
+    let mut description = format!("{} {}", req.method(), req.path());
+    if let Some(RequestId(request_id)) = req.extensions().get::<RequestId>() {
+        description.push_str(&format!(" ({})", request_id));
+    }
+    lifecycle.in_flight.lock().unwrap().insert(id, description);
+    let mut guard = InFlight {
+        lifecycle,
+        id,
+        finished: false,
+    };
+
+    let result = next.call(req).await;
+
+    guard.finished = true;
+    if guard.lifecycle.is_draining() {
+        guard.lifecycle.drained.fetch_add(1, Ordering::Relaxed);
+    }
+    result
+}
+
+#[cfg(unix)]
+async fn terminate() {
+    use actix_web::rt::signal::unix::{SignalKind, signal};
+    match signal(SignalKind::terminate()) {
+        Ok(mut sigterm) => {
+            sigterm.recv().await;
+        }
+        Err(e) => {
+            tracing::warn!("Cannot listen for SIGTERM: {}", e);
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn terminate() {
+    std::future::pending::<()>().await;
+}
+
+/// Señal de parada para `HttpServer::shutdown_signal`: espera a SIGTERM o Ctrl+C,
+/// marca el servidor como drenando y espera `drain_seconds` antes de que actix
+/// deje de aceptar conexiones.
+pub async fn signal(lifecycle: web::Data<Lifecycle>, config: ShutdownConfig) {
+    tokio::select! {
+        _ = terminate() => {}
+        _ = actix_web::rt::signal::ctrl_c() => {}
+    }
+    lifecycle.draining.store(true, Ordering::SeqCst);
+    tracing::info!(
+        in_flight = lifecycle.in_flight(),
+        "Shutdown requested; draining for {}s, then waiting up to {}s for in-flight requests",
+        config.drain_seconds,
+        config.timeout_seconds
+    );
+    tokio::time::sleep(Duration::from_secs(config.drain_seconds)).await;
+}
+
+/// Tras parar el servidor: cierra el pool y resume lo que se ha cortado.
+pub async fn finish(lifecycle: &Lifecycle, db: DatabaseConnection) {
+    let aborted: Vec<String> = lifecycle
+        .in_flight
+        .lock()
+        .unwrap()
+        .drain()
+        .map(|(_, description)| description)
+        .collect();
+    let drained = lifecycle.drained.load(Ordering::Relaxed);
+
+    if let Err(e) = db.close().await {
+        tracing::warn!("Closing the database pool failed: {}", e);
+    }
+
+    if aborted.is_empty() {
+        tracing::info!(
+            drained,
+            aborted = 0,
+            "Shutdown complete; {} requests finished during drain, none aborted",
+            drained
+        );
+    } else {
+        tracing::warn!(
+            drained,
+            aborted = aborted.len(),
+            "Shutdown complete; {} requests finished during drain, {} aborted: {}",
+            drained,
+            aborted.len(),
+            aborted.join(", ")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(lifecycle: &web::Data<Lifecycle>, id: u64) -> InFlight {
+        lifecycle
+            .in_flight
+            .lock()
+            .unwrap()
+            .insert(id, format!("GET /{}", id));
+        InFlight {
+            lifecycle: lifecycle.clone(),
+            id,
+            finished: false,
+        }
+    }
+
+    #[test]
+    fn dropped_requests_leave_no_entry() {
+        let lifecycle = web::Data::new(Lifecycle::default());
+
+        // El cliente cierra la conexión: el futuro se descarta sin terminar.
+        drop(track(&lifecycle, 1));
+        let mut finished = track(&lifecycle, 2);
+        finished.finished = true;
+        drop(finished);
+
+        assert_eq!(lifecycle.in_flight(), 0);
+    }
+
+    #[test]
+    fn requests_cut_while_draining_are_kept() {
+        let lifecycle = web::Data::new(Lifecycle::default());
+        let cut = track(&lifecycle, 1);
+        let mut finished = track(&lifecycle, 2);
+        lifecycle.draining.store(true, Ordering::SeqCst);
+
+        finished.finished = true;
+        drop(finished);
+        drop(cut);
+
+        let in_flight = lifecycle.in_flight.lock().unwrap();
+        assert_eq!(in_flight.keys().collect::<Vec<_>>(), [&1]);
+    }
+}
@@ -0,0 +1,408 @@
+// Logs estructurados con `tracing`.
+//
+// Cada petición abre un span `request` con método, ruta, estado, latencia e
+// identificador; al cerrarse se escribe una línea con todos esos campos. SeaORM
+// abre un span (nivel trace) por consulta dentro del span de la petición, de modo
+// que cada consulta aparece con su SQL y su duración.
+//
+// El identificador de la petición se toma de `X-Request-Id` si el cliente lo
+// envía (y es razonable) o se genera. Se devuelve en la cabecera `X-Request-Id`
+// de la respuesta y, en las respuestas de error, también en el cuerpo, para poder
+// buscar en los logs el error que ve el cliente.
+//
+// Si hay un colector configurado (`OTEL_EXPORTER_OTLP_ENDPOINT`), los mismos spans
+// se exportan por OTLP/HTTP. Un `traceparent` recibido (W3C Trace Context) hace que
+// el span de la petición continúe la traza del cliente.
+
+use crate::config::{LogConfig, LogFormat, OtelConfig};
+use crate::problem::PROBLEM_CONTENT_TYPE;
+use actix_web::body::{self, BoxBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpMessage, HttpResponse};
+use opentelemetry::propagation::{Extractor, TextMapPropagator};
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::Resource;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use std::time::Instant;
+use tracing::Instrument;
+use tracing::field::Empty;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+/// Instala el subscriber global. Devuelve el proveedor de trazas OTLP, si está
+/// configurado, para vaciarlo al apagar el servidor.
+pub fn init(log: &LogConfig, otel: &OtelConfig) -> Option<SdkTracerProvider> {
+    let filter = EnvFilter::try_new(&log.filter).unwrap_or_else(|e| {
+        eprintln!("RUST_LOG: invalid filter ({}); using \"info\"", e);
+        EnvFilter::new("info")
+    });
+    let fmt = tracing_subscriber::fmt::layer().with_span_events(FmtSpan::CLOSE);
+    let fmt = match log.format {
+        LogFormat::Pretty => fmt.boxed(),
+        LogFormat::Json => fmt
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .boxed(),
+    };
+
+    let provider = otel.endpoint.as_deref().and_then(|endpoint| {
+        tracer_provider(endpoint, &otel.service_name)
+            .map_err(|e| {
+                eprintln!(
+                    "OTEL_EXPORTER_OTLP_ENDPOINT: {}; traces are not exported",
+                    e
+                )
+            })
+            .ok()
+    });
+    let otel_layer = provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer().with_tracer(provider.tracer(otel.service_name.clone()))
+    });
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt)
+        .with(otel_layer)
+        .init();
+    if let (Some(endpoint), Some(_)) = (&otel.endpoint, &provider) {
+        tracing::info!("Exporting traces to {}", endpoint);
+    }
+    provider
+}
+
+/// Instala el subscriber global para los subcomandos de la CLI: el formato de
+/// `LOG_FORMAT`, sin spans ni exportación de trazas, y por defecto sin el SQL de
+/// cada consulta. Escribe en stderr, para dejar stdout a la salida del comando.
+pub fn init_cli(format: LogFormat) {
+    let filter = std::env::var("RUST_LOG")
+        .ok()
+        .and_then(|filter| EnvFilter::try_new(filter).ok())
+        .unwrap_or_else(|| EnvFilter::new("info,sqlx=warn"));
+    let fmt = tracing_subscriber::fmt::layer().with_writer(std::io::stderr);
+    let fmt = match format {
+        LogFormat::Pretty => fmt.boxed(),
+        LogFormat::Json => fmt.json().boxed(),
+    };
+    tracing_subscriber::registry().with(filter).with(fmt).init();
+}
+
+fn tracer_provider(
+    endpoint: &str,
+    service_name: &str,
+) -> Result<SdkTracerProvider, opentelemetry_otlp::ExporterBuildError> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+        .build()?;
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder()
+                .with_service_name(service_name.to_string())
+                .build(),
+        )
+        .build())
+}
+
+/// Lectura de las cabeceras de actix para el propagador de OpenTelemetry.
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longitud máxima de un `X-Request-Id` recibido; los más largos se sustituyen.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Identificador de la petición, disponible en las extensiones.
+#[derive(Clone, Debug)]
+pub struct RequestId(pub String);
+
+tokio::task_local! {
+    /// Identificador de la petición en curso, para el registro de auditoría.
+    static REQUEST_ID: String;
+}
+
+/// Identificador de la petición en curso; fuera de una petición (CLI) no hay.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// El `X-Request-Id` recibido, si es ASCII visible y no demasiado largo.
+fn incoming_request_id(req: &ServiceRequest) -> Option<String> {
+    let value = req.headers().get(REQUEST_ID_HEADER)?.to_str().ok()?.trim();
+    let valid = !value.is_empty()
+        && value.len() <= MAX_REQUEST_ID_LEN
+        && value.bytes().all(|b| b.is_ascii_graphic());
+    valid.then(|| value.to_string())
+}
+
+/// Middleware: asigna el identificador de la petición, la envuelve en un span
+/// `request` y anota en él el estado y la latencia de la respuesta.
+pub async fn trace_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, Error> {
+    let request_id = incoming_request_id(&req).unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    req.extensions_mut().insert(RequestId(request_id.clone()));
+
+    let route = req
+        .match_pattern()
+        .unwrap_or_else(|| req.path().to_string());
+    let span = tracing::info_span!(
+        "request",
+        method = %req.method(),
+        route = %route,
+        request_id = %request_id,
+        status = Empty,
+        latency_ms = Empty,
+        otel.name = %format!("{} {}", req.method(), route),
+        otel.kind = "server",
+        otel.status_code = Empty,
+    );
+    // Continúa la traza del cliente si envía `traceparent`. Sin exportador no hay
+    // contexto que asignar y el error se ignora.
+    let parent = TraceContextPropagator::new().extract(&HeaderExtractor(req.headers()));
+    let _ = span.set_parent(parent);
+
+    let started = Instant::now();
+    let result = REQUEST_ID
+        .scope(request_id.clone(), next.call(req).instrument(span.clone()))
+        .await;
+    let status = match &result {
+        Ok(response) => response.status(),
+        Err(e) => e.as_response_error().status_code(),
+    };
+    span.record("status", status.as_u16());
+    span.record("latency_ms", started.elapsed().as_millis() as u64);
+    if status.is_server_error() {
+        span.record("otel.status_code", "error");
+        span.in_scope(|| tracing::error!(status = status.as_u16(), "request failed"));
+    }
+    let response = result?.map_into_boxed_body();
+
+    let mut response = if status.is_client_error() || status.is_server_error() {
+        with_request_id_in_body(response, &request_id).await
+    } else {
+        response
+    };
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response
+            .headers_mut()
+            .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    }
+    Ok(response)
+}
+
+/// Añade el identificador al cuerpo de una respuesta de error: como miembro
+/// `request_id` si es un objeto JSON (problem+json incluido) o como una línea
+/// final si es texto. Los demás cuerpos se dejan como están.
+async fn with_request_id_in_body(
+    response: ServiceResponse<BoxBody>,
+    request_id: &str,
+) -> ServiceResponse<BoxBody> {
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .map(|v| v.trim().to_ascii_lowercase());
+    let is_json = matches!(
+        content_type.as_deref(),
+        Some("application/json") | Some(PROBLEM_CONTENT_TYPE)
+    );
+    let is_text = matches!(content_type.as_deref(), None | Some("text/plain"));
+    if !is_json && !is_text {
+        return response;
+    }
+
+    let (req, res) = response.into_parts();
+    let (res, body) = res.into_parts();
+    let bytes = match body::to_bytes(body).await {
+        Ok(bytes) => bytes,
+        // El cuerpo no se pudo leer: se responde sin él.
+        Err(_) => return ServiceResponse::new(req, res.set_body(BoxBody::new(()))),
+    };
+
+    let new_body = if is_json {
+        match serde_json::from_slice::<serde_json::Value>(&bytes) {
+            Ok(serde_json::Value::Object(mut object)) => {
+                object.insert("request_id".to_string(), request_id.into());
+                serde_json::to_vec(&object).ok()
+            }
+            _ => None,
+        }
+    } else {
+        let text = String::from_utf8_lossy(&bytes);
+        let text = text.trim_end();
+        let text = if text.is_empty() {
+            format!("Request ID: {}", request_id)
+        } else {
+            format!("{}\nRequest ID: {}", text, request_id)
+        };
+        Some(text.into_bytes())
+    };
+
+    let mut res: HttpResponse<BoxBody> =
+        res.set_body(BoxBody::new(new_body.unwrap_or_else(|| bytes.to_vec())));
+    // El tamaño ha cambiado; actix lo recalcula a partir del cuerpo.
+    res.headers_mut().remove(header::CONTENT_LENGTH);
+    ServiceResponse::new(req, res)
+}
+
+#[cfg(test)]
+mod tests {
+    // Exportación OTLP contra un colector falso en el mismo proceso.
+
+    use super::*;
+    use actix_web::middleware::from_fn;
+    use actix_web::{App, test, web};
+    use opentelemetry_proto::tonic::collector::trace::v1::ExportTraceServiceRequest;
+    use opentelemetry_proto::tonic::trace::v1::Span;
+    use opentelemetry_proto::tonic::trace::v1::span::SpanKind;
+    use prost::Message;
+    use sea_orm::{ConnectionTrait, Database, DatabaseConnection, DbBackend, Statement};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const PARENT_SPAN_ID: &str = "00f067aa0ba902b7";
+
+    /// Colector OTLP/HTTP mínimo: guarda los spans de cada petición y responde
+    /// `200`. Devuelve su URL y los spans recibidos.
+    fn start_collector() -> (String, Arc<Mutex<Vec<Span>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let spans = Arc::new(Mutex::new(Vec::new()));
+        let received = spans.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let received = received.clone();
+                thread::spawn(move || serve(stream, &received));
+            }
+        });
+        (endpoint, spans)
+    }
+
+    /// Atiende las peticiones de una conexión hasta que el cliente la cierra.
+    fn serve(stream: TcpStream, received: &Mutex<Vec<Span>>) {
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut writer = stream;
+        let mut line = String::new();
+        loop {
+            // Línea de petición y cabeceras; solo interesa `Content-Length`.
+            let mut content_length = 0;
+            line.clear();
+            if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                return;
+            }
+            loop {
+                line.clear();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                    return;
+                }
+                let header = line.trim_end();
+                if header.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = header.split_once(':')
+                    && name.eq_ignore_ascii_case("content-length")
+                {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+
+            let request = ExportTraceServiceRequest::decode(body.as_slice()).unwrap();
+            received.lock().unwrap().extend(
+                request
+                    .resource_spans
+                    .into_iter()
+                    .flat_map(|resource| resource.scope_spans)
+                    .flat_map(|scope| scope.spans),
+            );
+            let response = "HTTP/1.1 200 OK\r\n\
+                            content-type: application/x-protobuf\r\n\
+                            content-length: 0\r\n\r\n";
+            if writer.write_all(response.as_bytes()).is_err() {
+                return;
+            }
+        }
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    async fn query(db: web::Data<DatabaseConnection>) -> HttpResponse {
+        db.query_one(Statement::from_string(DbBackend::Sqlite, "SELECT 1"))
+            .await
+            .unwrap();
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_web::test]
+    async fn exports_request_and_query_spans() {
+        let (endpoint, spans) = start_collector();
+        let provider = tracer_provider(&endpoint, "movies-api-rest-test").unwrap();
+        let subscriber = tracing_subscriber::registry()
+            .with(EnvFilter::new("info,sea_orm::database=trace"))
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let app = test::init_service(
+            App::new()
+                .wrap(from_fn(trace_requests))
+                .app_data(web::Data::new(db))
+                .route("/query", web::get().to(query)),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri("/query")
+            .insert_header((
+                "traceparent",
+                format!("00-{}-{}-01", TRACE_ID, PARENT_SPAN_ID),
+            ))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert!(res.status().is_success());
+        provider.force_flush().unwrap();
+
+        let spans = spans.lock().unwrap();
+        let server = spans
+            .iter()
+            .find(|span| span.kind == SpanKind::Server as i32)
+            .expect("no server span exported");
+        assert_eq!(server.name, "GET /query");
+        // La petición continúa la traza del `traceparent`.
+        assert_eq!(hex(&server.trace_id), TRACE_ID);
+        assert_eq!(hex(&server.parent_span_id), PARENT_SPAN_ID);
+
+        let query = spans
+            .iter()
+            .find(|span| span.name == "query_one")
+            .expect("no query span exported");
+        assert_eq!(query.trace_id, server.trace_id);
+        assert_eq!(query.parent_span_id, server.span_id);
+    }
+}
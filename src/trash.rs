@@ -0,0 +1,560 @@
+// Papelera: borrado lógico de películas, directores, actores y géneros.
+//
+// Los `DELETE` solo rellenan `deleted_at`; las filas dejan de aparecer en los
+// listados, las búsquedas, las facetas y las exportaciones, pero conservan sus
+// relaciones, así que al restaurarlas vuelven tal como estaban. `trash purge`
+// (ver `cli.rs`) borra de verdad lo que lleva en la papelera más de un plazo.
+
+use crate::audit::{self, Audited};
+use crate::auth::{self, Authorized, require};
+use crate::models::{
+    AuditAction, actor, director, genre, movie, movie_actor, movie_genre, movie_revision,
+};
+use actix_web::{HttpResponse, Responder, web};
+use chrono::Utc;
+use sea_orm::prelude::DateTimeUtc;
+use sea_orm::sea_query::{Expr, SelectStatement};
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, DatabaseConnection, DatabaseTransaction, DbErr,
+    EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, QueryTrait,
+    TransactionTrait,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Días en la papelera tras los que `trash purge` borra una fila si no se indica
+/// otro plazo.
+pub const DEFAULT_RETENTION_DAYS: i64 = 30;
+
+/// Manda a la papelera la fila `id` de `E` si sigue en `version` y lo anota en
+/// el registro de auditoría. Devuelve `false` si no existe, ya estaba en la
+/// papelera o ha cambiado de versión. `columns` son las de id, `deleted_at`,
+/// `version`, `updated_at` y `updated_by`: el borrado cuenta como una
+/// modificación más (ver `models::track_changes`).
+pub async fn soft_delete<E>(
+    db: &DatabaseConnection,
+    columns: [E::Column; 5],
+    id: i32,
+    version: i32,
+) -> Result<bool, DbErr>
+where
+    E: EntityTrait,
+    E::Model: Audited,
+{
+    let txn = db.begin().await?;
+    if !soft_delete_in::<_, E>(&txn, columns, id, version).await? {
+        return Ok(false);
+    }
+    txn.commit().await?;
+    Ok(true)
+}
+
+/// `soft_delete` dentro de una transacción que confirma quien llama, para hacer
+/// comprobaciones que deben ver el borrado (ver `delete_director`).
+pub async fn soft_delete_in<C, E>(
+    txn: &C,
+    [
+        id_col,
+        deleted_col,
+        version_col,
+        updated_at_col,
+        updated_by_col,
+    ]: [E::Column; 5],
+    id: i32,
+    version: i32,
+) -> Result<bool, DbErr>
+where
+    C: ConnectionTrait,
+    E: EntityTrait,
+    E::Model: Audited,
+{
+    let Some(before) = E::find()
+        .filter(id_col.eq(id))
+        .filter(deleted_col.is_null())
+        .filter(version_col.eq(version))
+        .one(txn)
+        .await?
+    else {
+        return Ok(false);
+    };
+    let now = Utc::now();
+    let result = E::update_many()
+        .col_expr(deleted_col, Expr::value(now))
+        .col_expr(version_col, Expr::col(version_col).add(1))
+        .col_expr(updated_at_col, Expr::value(now))
+        .col_expr(updated_by_col, Expr::value(auth::current_actor()))
+        .filter(id_col.eq(id))
+        .filter(version_col.eq(version))
+        .exec(txn)
+        .await?;
+    if result.rows_affected == 0 {
+        return Ok(false);
+    }
+    let after = E::find().filter(id_col.eq(id)).one(txn).await?;
+    audit::record(txn, AuditAction::Delete, Some(&before), after.as_ref()).await?;
+    Ok(true)
+}
+
+/// Saca de la papelera la fila `id` de `E`, lo anota en el registro de auditoría
+/// y la devuelve, o `None` si no estaba. `columns` como en `soft_delete`.
+async fn restore<E>(
+    db: &DatabaseConnection,
+    columns: [E::Column; 5],
+    id: i32,
+) -> Result<Option<E::Model>, DbErr>
+where
+    E: EntityTrait,
+    E::Model: Audited,
+{
+    let txn = db.begin().await?;
+    let restored = restore_in::<E>(&txn, columns, id).await?;
+    if restored.is_some() {
+        txn.commit().await?;
+    }
+    Ok(restored)
+}
+
+/// `restore` dentro de una transacción que confirma quien llama (ver
+/// `restore_movie`).
+async fn restore_in<E>(
+    txn: &DatabaseTransaction,
+    [
+        id_col,
+        deleted_col,
+        version_col,
+        updated_at_col,
+        updated_by_col,
+    ]: [E::Column; 5],
+    id: i32,
+) -> Result<Option<E::Model>, DbErr>
+where
+    E: EntityTrait,
+    E::Model: Audited,
+{
+    let Some(before) = E::find()
+        .filter(id_col.eq(id))
+        .filter(deleted_col.is_not_null())
+        .one(txn)
+        .await?
+    else {
+        return Ok(None);
+    };
+    E::update_many()
+        .col_expr(deleted_col, Expr::value(Option::<DateTimeUtc>::None))
+        .col_expr(version_col, Expr::col(version_col).add(1))
+        .col_expr(updated_at_col, Expr::value(Utc::now()))
+        .col_expr(updated_by_col, Expr::value(auth::current_actor()))
+        .filter(id_col.eq(id))
+        .exec(txn)
+        .await?;
+    let after = E::find().filter(id_col.eq(id)).one(txn).await?;
+    audit::record(txn, AuditAction::Restore, Some(&before), after.as_ref()).await?;
+    Ok(after)
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TrashItem {
+    pub id: i32,
+    /// Nombre, o título en las películas.
+    pub name: String,
+    pub deleted_at: DateTimeUtc,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct Trash {
+    pub movies: Vec<TrashItem>,
+    pub directors: Vec<TrashItem>,
+    pub actors: Vec<TrashItem>,
+    pub genres: Vec<TrashItem>,
+}
+
+/// Filas de `E` en la papelera, las borradas más recientemente primero.
+async fn trashed<E: EntityTrait>(
+    db: &DatabaseConnection,
+    [id_col, name_col, deleted_col]: [E::Column; 3],
+) -> Result<Vec<TrashItem>, DbErr> {
+    let rows: Vec<(i32, String, DateTimeUtc)> = E::find()
+        .select_only()
+        .columns([id_col, name_col, deleted_col])
+        .filter(deleted_col.is_not_null())
+        .order_by_desc(deleted_col)
+        .order_by_asc(id_col)
+        .into_tuple()
+        .all(db)
+        .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(id, name, deleted_at)| TrashItem {
+            id,
+            name,
+            deleted_at,
+        })
+        .collect())
+}
+
+async fn load_trash(db: &DatabaseConnection) -> Result<Trash, DbErr> {
+    Ok(Trash {
+        movies: trashed::<movie::Entity>(
+            db,
+            [
+                movie::Column::Id,
+                movie::Column::Title,
+                movie::Column::DeletedAt,
+            ],
+        )
+        .await?,
+        directors: trashed::<director::Entity>(
+            db,
+            [
+                director::Column::Id,
+                director::Column::Name,
+                director::Column::DeletedAt,
+            ],
+        )
+        .await?,
+        actors: trashed::<actor::Entity>(
+            db,
+            [
+                actor::Column::Id,
+                actor::Column::Name,
+                actor::Column::DeletedAt,
+            ],
+        )
+        .await?,
+        genres: trashed::<genre::Entity>(
+            db,
+            [
+                genre::Column::Id,
+                genre::Column::Name,
+                genre::Column::DeletedAt,
+            ],
+        )
+        .await?,
+    })
+}
+
+// GET /trash
+#[utoipa::path(
+    get,
+    path = "/trash",
+    responses(
+        (status = 200, description = "Películas, directores, actores y géneros borrados", body = Trash),
+        (status = 403, description = "Requiere rol editor", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    tag = "trash"
+)]
+pub async fn list_trash(
+    _auth: Authorized<require::Editor>,
+    db: web::Data<DatabaseConnection>,
+) -> impl Responder {
+    match load_trash(db.get_ref()).await {
+        Ok(trash) => HttpResponse::Ok().json(trash),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
+
+// POST /movies/{id}/restore
+#[utoipa::path(
+    post,
+    path = "/movies/{id}/restore",
+    responses(
+        (status = 200, description = "Película restaurada con su reparto y sus géneros", body = movie::Model),
+        (status = 403, description = "Requiere rol editor", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "La película no está en la papelera"),
+        (status = 409, description = "El director de la película está en la papelera"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    params(
+        ("id" = i32, Path, description = "Id de la película")
+    ),
+    tag = "trash"
+)]
+pub async fn restore_movie(
+    _auth: Authorized<require::Editor>,
+    db: web::Data<DatabaseConnection>,
+    id: web::Path<i32>,
+) -> impl Responder {
+    let txn = match db.begin().await {
+        Ok(txn) => txn,
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    };
+    let model = match restore_in::<movie::Entity>(
+        &txn,
+        [
+            movie::Column::Id,
+            movie::Column::DeletedAt,
+            movie::Column::Version,
+            movie::Column::UpdatedAt,
+            movie::Column::UpdatedBy,
+        ],
+        id.into_inner(),
+    )
+    .await
+    {
+        Ok(Some(model)) => model,
+        Ok(None) => return HttpResponse::NotFound().body("Movie not found in trash"),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    };
+    // Una película visible siempre tiene un director visible. Se comprueba en la
+    // misma transacción que la restauración, para que un `DELETE /directors/{id}`
+    // simultáneo no mande el director a la papelera entre medias; si lo está, la
+    // transacción se descarta sin confirmar.
+    match director::Entity::find_by_id(model.director_id)
+        .filter(director::Column::DeletedAt.is_not_null())
+        .one(&txn)
+        .await
+    {
+        Ok(None) => {}
+        Ok(Some(director)) => {
+            return HttpResponse::Conflict().body(format!(
+                "Director {} is in the trash; restore it first",
+                director.id
+            ));
+        }
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+    match txn.commit().await {
+        Ok(()) => HttpResponse::Ok().json(model),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
+
+// POST /directors/{id}/restore
+#[utoipa::path(
+    post,
+    path = "/directors/{id}/restore",
+    responses(
+        (status = 200, description = "Director restaurado", body = director::Model),
+        (status = 403, description = "Requiere rol editor", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "El director no está en la papelera"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    params(
+        ("id" = i32, Path, description = "Id del director")
+    ),
+    tag = "trash"
+)]
+pub async fn restore_director(
+    _auth: Authorized<require::Editor>,
+    db: web::Data<DatabaseConnection>,
+    id: web::Path<i32>,
+) -> impl Responder {
+    match restore::<director::Entity>(
+        db.get_ref(),
+        [
+            director::Column::Id,
+            director::Column::DeletedAt,
+            director::Column::Version,
+            director::Column::UpdatedAt,
+            director::Column::UpdatedBy,
+        ],
+        id.into_inner(),
+    )
+    .await
+    {
+        Ok(Some(model)) => HttpResponse::Ok().json(model),
+        Ok(None) => HttpResponse::NotFound().body("Director not found in trash"),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
+
+// POST /actors/{id}/restore
+#[utoipa::path(
+    post,
+    path = "/actors/{id}/restore",
+    responses(
+        (status = 200, description = "Actor restaurado (vuelve a los repartos)", body = actor::Model),
+        (status = 403, description = "Requiere rol editor", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "El actor no está en la papelera"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    params(
+        ("id" = i32, Path, description = "Id del actor")
+    ),
+    tag = "trash"
+)]
+pub async fn restore_actor(
+    _auth: Authorized<require::Editor>,
+    db: web::Data<DatabaseConnection>,
+    id: web::Path<i32>,
+) -> impl Responder {
+    match restore::<actor::Entity>(
+        db.get_ref(),
+        [
+            actor::Column::Id,
+            actor::Column::DeletedAt,
+            actor::Column::Version,
+            actor::Column::UpdatedAt,
+            actor::Column::UpdatedBy,
+        ],
+        id.into_inner(),
+    )
+    .await
+    {
+        Ok(Some(model)) => HttpResponse::Ok().json(model),
+        Ok(None) => HttpResponse::NotFound().body("Actor not found in trash"),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
+
+// POST /genres/{id}/restore
+#[utoipa::path(
+    post,
+    path = "/genres/{id}/restore",
+    responses(
+        (status = 200, description = "Género restaurado (vuelve a las películas)", body = genre::Model),
+        (status = 403, description = "Requiere rol editor", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "El género no está en la papelera"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    params(
+        ("id" = i32, Path, description = "Id del género")
+    ),
+    tag = "trash"
+)]
+pub async fn restore_genre(
+    _auth: Authorized<require::Editor>,
+    db: web::Data<DatabaseConnection>,
+    id: web::Path<i32>,
+) -> impl Responder {
+    match restore::<genre::Entity>(
+        db.get_ref(),
+        [
+            genre::Column::Id,
+            genre::Column::DeletedAt,
+            genre::Column::Version,
+            genre::Column::UpdatedAt,
+            genre::Column::UpdatedBy,
+        ],
+        id.into_inner(),
+    )
+    .await
+    {
+        Ok(Some(model)) => HttpResponse::Ok().json(model),
+        Ok(None) => HttpResponse::NotFound().body("Genre not found in trash"),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
+
+/// Filas borradas definitivamente por `purge`.
+#[derive(Default)]
+pub struct PurgeReport {
+    pub movies: u64,
+    pub directors: u64,
+    pub actors: u64,
+    pub genres: u64,
+    /// Directores caducados que no se pueden borrar porque alguna película de la
+    /// papelera (aún dentro del plazo) los sigue referenciando.
+    pub directors_kept: u64,
+}
+
+/// Ids de las filas de `E` que están en la papelera desde antes de `cutoff`.
+fn expired<E: EntityTrait>(
+    [id_col, deleted_col]: [E::Column; 2],
+    cutoff: DateTimeUtc,
+) -> SelectStatement {
+    E::find()
+        .select_only()
+        .column(id_col)
+        .filter(deleted_col.lt(cutoff))
+        .into_query()
+}
+
+/// Borra las filas de `E` que cumplen `condition` y anota cada una en el
+/// registro de auditoría.
+async fn purge_rows<E>(txn: &DatabaseTransaction, condition: Condition) -> Result<u64, DbErr>
+where
+    E: EntityTrait,
+    E::Model: Audited,
+{
+    let rows = E::find().filter(condition.clone()).all(txn).await?;
+    for row in &rows {
+        audit::record(txn, AuditAction::Purge, Some(row), None).await?;
+    }
+    Ok(E::delete_many()
+        .filter(condition)
+        .exec(txn)
+        .await?
+        .rows_affected)
+}
+
+/// Borra definitivamente, en una transacción, lo que está en la papelera desde
+/// antes de `cutoff`, junto con sus relaciones y, en las películas, su historial.
+pub async fn purge(db: &DatabaseConnection, cutoff: DateTimeUtc) -> Result<PurgeReport, DbErr> {
+    let txn = db.begin().await?;
+    let mut report = PurgeReport::default();
+
+    let movies = expired::<movie::Entity>([movie::Column::Id, movie::Column::DeletedAt], cutoff);
+    purge_rows::<movie_actor::Entity>(
+        &txn,
+        Condition::all().add(movie_actor::Column::MovieId.in_subquery(movies.clone())),
+    )
+    .await?;
+    purge_rows::<movie_genre::Entity>(
+        &txn,
+        Condition::all().add(movie_genre::Column::MovieId.in_subquery(movies.clone())),
+    )
+    .await?;
+    movie_revision::Entity::delete_many()
+        .filter(movie_revision::Column::MovieId.in_subquery(movies))
+        .exec(&txn)
+        .await?;
+    report.movies = purge_rows::<movie::Entity>(
+        &txn,
+        Condition::all().add(movie::Column::DeletedAt.lt(cutoff)),
+    )
+    .await?;
+
+    purge_rows::<movie_actor::Entity>(
+        &txn,
+        Condition::all().add(
+            movie_actor::Column::ActorId.in_subquery(expired::<actor::Entity>(
+                [actor::Column::Id, actor::Column::DeletedAt],
+                cutoff,
+            )),
+        ),
+    )
+    .await?;
+    report.actors = purge_rows::<actor::Entity>(
+        &txn,
+        Condition::all().add(actor::Column::DeletedAt.lt(cutoff)),
+    )
+    .await?;
+
+    purge_rows::<movie_genre::Entity>(
+        &txn,
+        Condition::all().add(
+            movie_genre::Column::GenreId.in_subquery(expired::<genre::Entity>(
+                [genre::Column::Id, genre::Column::DeletedAt],
+                cutoff,
+            )),
+        ),
+    )
+    .await?;
+    report.genres = purge_rows::<genre::Entity>(
+        &txn,
+        Condition::all().add(genre::Column::DeletedAt.lt(cutoff)),
+    )
+    .await?;
+
+    // `movies.director_id` es obligatorio: solo se borran los directores sin películas.
+    let referenced = movie::Entity::find()
+        .select_only()
+        .column(movie::Column::DirectorId)
+        .into_query();
+    report.directors = purge_rows::<director::Entity>(
+        &txn,
+        Condition::all()
+            .add(director::Column::DeletedAt.lt(cutoff))
+            .add(director::Column::Id.not_in_subquery(referenced)),
+    )
+    .await?;
+    report.directors_kept = director::Entity::find()
+        .filter(director::Column::DeletedAt.lt(cutoff))
+        .count(&txn)
+        .await?;
+
+    txn.commit().await?;
+    Ok(report)
+}
@@ -0,0 +1,426 @@
+// Cuentas de usuario y sesiones.
+//
+// El login devuelve un access token JWT de corta duración (`ACCESS_TTL_MINUTES`)
+// y un refresh token opaco que se guarda hasheado en `refresh_tokens`. Cada
+// `POST /auth/refresh` revoca el refresh token usado y entrega uno nuevo; el
+// logout lo revoca. Las contraseñas se guardan con Argon2.
+
+use crate::auth::{Authorized, hash_key, random_token, require};
+use crate::models::{Role, refresh_token, user};
+use actix_web::dev::Payload;
+use actix_web::http::header;
+use actix_web::{FromRequest, HttpRequest, HttpResponse, Responder, web};
+use argon2::Argon2;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use chrono::{Duration, Utc};
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use sea_orm::ActiveValue::Set;
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, SqlErr,
+};
+use serde::{Deserialize, Serialize};
+use std::future::{Ready, ready};
+use utoipa::ToSchema;
+
+/// Vida de los access tokens.
+const ACCESS_TTL_MINUTES: i64 = 15;
+
+/// Vida de los refresh tokens.
+const REFRESH_TTL_DAYS: i64 = 30;
+
+const REFRESH_PREFIX: &str = "rt_";
+
+const MIN_PASSWORD_LEN: usize = 8;
+
+/// Claves para firmar y verificar los JWT (HS256), a partir de `JWT_SECRET`.
+#[derive(Clone)]
+pub struct JwtKeys {
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+}
+
+impl JwtKeys {
+    /// Lee `JWT_SECRET`. Si no está definido se usa un secreto aleatorio: los
+    /// tokens dejan de valer al reiniciar el servidor.
+    pub fn from_env() -> Self {
+        let secret = match std::env::var("JWT_SECRET") {
+            Ok(secret) if !secret.is_empty() => secret,
+            _ => {
+                tracing::warn!("JWT_SECRET not set; using a random secret for this run");
+                random_token("")
+            }
+        };
+        JwtKeys {
+            encoding: EncodingKey::from_secret(secret.as_bytes()),
+            decoding: DecodingKey::from_secret(secret.as_bytes()),
+        }
+    }
+
+    /// Valida firma y caducidad de un access token.
+    pub fn decode(&self, token: &str) -> Option<Claims> {
+        jsonwebtoken::decode::<Claims>(token, &self.decoding, &Validation::default())
+            .ok()
+            .map(|data| data.claims)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Claims {
+    /// Id del usuario.
+    pub sub: i32,
+    pub email: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+/// Usuario autenticado con un access token en `Authorization: Bearer`.
+/// Como extractor, responde `401` si falta el token o no es válido.
+pub struct AuthUser {
+    pub id: i32,
+}
+
+impl FromRequest for AuthUser {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let claims = req
+            .app_data::<web::Data<JwtKeys>>()
+            .zip(
+                req.headers()
+                    .get(header::AUTHORIZATION)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.strip_prefix("Bearer ")),
+            )
+            .and_then(|(keys, token)| keys.decode(token.trim()));
+        ready(match claims {
+            Some(claims) => Ok(AuthUser { id: claims.sub }),
+            None => Err(actix_web::error::ErrorUnauthorized(
+                "Missing or invalid access token",
+            )),
+        })
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct Credentials {
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SetRole {
+    pub role: Role,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: &'static str,
+    /// Segundos hasta que caduca `access_token`.
+    pub expires_in: i64,
+    pub refresh_token: String,
+}
+
+fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// Crea un access token y un refresh token nuevos para `user`.
+async fn issue_tokens(
+    db: &DatabaseConnection,
+    keys: &JwtKeys,
+    user: &user::Model,
+) -> Result<TokenResponse, DbErr> {
+    let now = Utc::now();
+    let claims = Claims {
+        sub: user.id,
+        email: user.email.clone(),
+        iat: now.timestamp(),
+        exp: (now + Duration::minutes(ACCESS_TTL_MINUTES)).timestamp(),
+    };
+    let access_token = jsonwebtoken::encode(&Header::default(), &claims, &keys.encoding)
+        .map_err(|e| DbErr::Custom(format!("JWT error: {}", e)))?;
+
+    let refresh = random_token(REFRESH_PREFIX);
+    refresh_token::ActiveModel {
+        user_id: Set(user.id),
+        token_hash: Set(hash_key(&refresh)),
+        created_at: Set(now),
+        expires_at: Set(now + Duration::days(REFRESH_TTL_DAYS)),
+        revoked_at: Set(None),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    Ok(TokenResponse {
+        access_token,
+        token_type: "Bearer",
+        expires_in: ACCESS_TTL_MINUTES * 60,
+        refresh_token: refresh,
+    })
+}
+
+/// Revoca el refresh token si sigue activo y devuelve su fila. La revocación es
+/// un único `UPDATE` condicionado a `revoked_at IS NULL`: de dos peticiones
+/// simultáneas con el mismo token solo una lo revoca, y la otra recibe `None`
+/// como si lo hubiera reutilizado.
+async fn revoke_refresh(
+    db: &DatabaseConnection,
+    token: &str,
+) -> Result<Option<refresh_token::Model>, DbErr> {
+    let token_hash = hash_key(token.trim());
+    let result = refresh_token::Entity::update_many()
+        .col_expr(refresh_token::Column::RevokedAt, Expr::value(Utc::now()))
+        .filter(refresh_token::Column::TokenHash.eq(token_hash.as_str()))
+        .filter(refresh_token::Column::RevokedAt.is_null())
+        .exec(db)
+        .await?;
+    if result.rows_affected != 1 {
+        return Ok(None);
+    }
+    refresh_token::Entity::find()
+        .filter(refresh_token::Column::TokenHash.eq(token_hash))
+        .one(db)
+        .await
+}
+
+// POST /auth/register
+#[utoipa::path(
+    post,
+    path = "/auth/register",
+    request_body = Credentials,
+    responses(
+        (status = 201, description = "Usuario creado", body = user::Model),
+        (status = 400, description = "Email o contraseña no válidos"),
+        (status = 409, description = "El email ya está registrado"),
+        (status = 500, description = "Internal Server Error")
+    )
+)]
+pub async fn register(
+    db: web::Data<DatabaseConnection>,
+    form: web::Json<Credentials>,
+) -> impl Responder {
+    let email = normalize_email(&form.email);
+    if !email.contains('@') {
+        return HttpResponse::BadRequest().body("Invalid email");
+    }
+    if form.password.chars().count() < MIN_PASSWORD_LEN {
+        return HttpResponse::BadRequest().body(format!(
+            "Password must be at least {} characters",
+            MIN_PASSWORD_LEN
+        ));
+    }
+
+    // Atajo para no calcular el hash en vano; el índice único de `email` es el que
+    // decide si dos registros simultáneos piden el mismo.
+    match user::Entity::find()
+        .filter(user::Column::Email.eq(email.as_str()))
+        .one(db.get_ref())
+        .await
+    {
+        Ok(Some(_)) => return HttpResponse::Conflict().body("Email already registered"),
+        Ok(None) => {}
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+
+    // Argon2 es costoso a propósito: fuera del hilo del servidor.
+    let password = form.password.clone();
+    let hashed = web::block(move || {
+        let salt = SaltString::generate(&mut rand::rngs::OsRng);
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+    })
+    .await;
+    let password_hash = match hashed {
+        Ok(Ok(hash)) => hash,
+        Ok(Err(e)) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    };
+
+    let user = user::ActiveModel {
+        email: Set(email),
+        password_hash: Set(password_hash),
+        created_at: Set(Utc::now()),
+        role: Set(Role::Viewer),
+        ..Default::default()
+    };
+    match user.insert(db.get_ref()).await {
+        Ok(model) => HttpResponse::Created().json(model),
+        Err(e) if matches!(e.sql_err(), Some(SqlErr::UniqueConstraintViolation(_))) => {
+            HttpResponse::Conflict().body("Email already registered")
+        }
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
+
+// POST /auth/login
+#[utoipa::path(
+    post,
+    path = "/auth/login",
+    request_body = Credentials,
+    responses(
+        (status = 200, description = "Sesión iniciada", body = TokenResponse),
+        (status = 401, description = "Credenciales incorrectas"),
+        (status = 500, description = "Internal Server Error")
+    )
+)]
+pub async fn login(
+    db: web::Data<DatabaseConnection>,
+    keys: web::Data<JwtKeys>,
+    form: web::Json<Credentials>,
+) -> impl Responder {
+    let user = match user::Entity::find()
+        .filter(user::Column::Email.eq(normalize_email(&form.email)))
+        .one(db.get_ref())
+        .await
+    {
+        Ok(Some(user)) => user,
+        Ok(None) => return HttpResponse::Unauthorized().body("Invalid credentials"),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    };
+
+    let password = form.password.clone();
+    let stored = user.password_hash.clone();
+    let verified = web::block(move || {
+        PasswordHash::new(&stored)
+            .map(|hash| {
+                Argon2::default()
+                    .verify_password(password.as_bytes(), &hash)
+                    .is_ok()
+            })
+            .unwrap_or(false)
+    })
+    .await;
+    match verified {
+        Ok(true) => {}
+        Ok(false) => return HttpResponse::Unauthorized().body("Invalid credentials"),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+
+    match issue_tokens(db.get_ref(), &keys, &user).await {
+        Ok(tokens) => HttpResponse::Ok().json(tokens),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
+
+// POST /auth/refresh
+#[utoipa::path(
+    post,
+    path = "/auth/refresh",
+    request_body = RefreshRequest,
+    responses(
+        (status = 200, description = "Tokens nuevos; el refresh token usado deja de valer", body = TokenResponse),
+        (status = 401, description = "Refresh token no válido, caducado o revocado"),
+        (status = 500, description = "Internal Server Error")
+    )
+)]
+pub async fn refresh(
+    db: web::Data<DatabaseConnection>,
+    keys: web::Data<JwtKeys>,
+    form: web::Json<RefreshRequest>,
+) -> impl Responder {
+    let stored = match revoke_refresh(db.get_ref(), &form.refresh_token).await {
+        Ok(Some(stored)) if stored.expires_at > Utc::now() => stored,
+        Ok(_) => return HttpResponse::Unauthorized().body("Invalid refresh token"),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    };
+
+    let user = match user::Entity::find_by_id(stored.user_id)
+        .one(db.get_ref())
+        .await
+    {
+        Ok(Some(user)) => user,
+        Ok(None) => return HttpResponse::Unauthorized().body("Invalid refresh token"),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    };
+
+    match issue_tokens(db.get_ref(), &keys, &user).await {
+        Ok(tokens) => HttpResponse::Ok().json(tokens),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
+
+// POST /auth/logout
+#[utoipa::path(
+    post,
+    path = "/auth/logout",
+    request_body = RefreshRequest,
+    responses(
+        (status = 204, description = "Refresh token revocado (o ya no era válido)"),
+        (status = 500, description = "Internal Server Error")
+    )
+)]
+pub async fn logout(
+    db: web::Data<DatabaseConnection>,
+    form: web::Json<RefreshRequest>,
+) -> impl Responder {
+    match revoke_refresh(db.get_ref(), &form.refresh_token).await {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
+
+// GET /auth/me
+#[utoipa::path(
+    get,
+    path = "/auth/me",
+    responses(
+        (status = 200, description = "Usuario de la sesión", body = user::Model),
+        (status = 401, description = "Falta el access token o no es válido"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn me(db: web::Data<DatabaseConnection>, auth: AuthUser) -> impl Responder {
+    match user::Entity::find_by_id(auth.id).one(db.get_ref()).await {
+        Ok(Some(user)) => HttpResponse::Ok().json(user),
+        Ok(None) => HttpResponse::Unauthorized().body("Unknown user"),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}
+
+// PUT /admin/users/{id}/role
+#[utoipa::path(
+    put,
+    path = "/admin/users/{id}/role",
+    request_body = SetRole,
+    responses(
+        (status = 200, description = "Rol actualizado", body = user::Model),
+        (status = 403, description = "Requiere rol admin", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "User not found"),
+        (status = 500, description = "Internal Server Error")
+    ),
+    params(
+        ("id" = i32, Path, description = "Id del usuario")
+    )
+)]
+pub async fn set_role(
+    _auth: Authorized<require::Admin>,
+    db: web::Data<DatabaseConnection>,
+    id: web::Path<i32>,
+    form: web::Json<SetRole>,
+) -> impl Responder {
+    let user = match user::Entity::find_by_id(id.into_inner())
+        .one(db.get_ref())
+        .await
+    {
+        Ok(Some(user)) => user,
+        Ok(None) => return HttpResponse::NotFound().body("User not found"),
+        Err(e) => return HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    };
+    let mut active: user::ActiveModel = user.into();
+    active.role = Set(form.role);
+    match active.update(db.get_ref()).await {
+        Ok(model) => HttpResponse::Ok().json(model),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Error: {}", e)),
+    }
+}